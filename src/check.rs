@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ansi_term::Colour::{Green, Red};
+use linked_hash_map::LinkedHashMap as Map;
+use regex::Regex;
+use serde_yaml::{from_value, Mapping, Value};
+
+use crate::commands::{alias_clashes, cmd_key_names, resolve, set_shell, Cmd, Format, Settings};
+use crate::interpreter;
+use crate::prepare::{get_working_dir, INLINE_PREFIX};
+use crate::utils::closest;
+
+// validate the whole config file and report every problem found with its location
+pub fn main(file_path: &Path) -> Result<i32, String> {
+    let content = match fs::read_to_string(file_path) {
+        Ok(t) => t,
+        Err(e) => return err!("Error opening {}:\n  {}", file_path.display(), e),
+    };
+    let format = Format::from_path(file_path);
+    let mut checker = Checker {
+        content: &content,
+        file_path,
+        format,
+        lines: format.find_keys(&content),
+        settings: Settings::default(),
+        unknown_keys: Vec::new(),
+        problems: Vec::new(),
+    };
+    let count = checker.check();
+    let mut problems = checker.problems;
+    problems.sort_by_key(|p| (p.line, p.column));
+    for p in &problems {
+        println!("{}:{}:{}: {}", file_path.display(), p.line, p.column, p.msg);
+    }
+    Ok(match problems.len() {
+        0 => {
+            printlnc!(Green, "{} is valid, {} commands found", file_path.display(), count);
+            0
+        }
+        c => {
+            printlnc!(
+                Red,
+                "{} problem{} found in {}",
+                c,
+                if c == 1 { "" } else { "s" },
+                file_path.display()
+            );
+            1
+        }
+    })
+}
+
+struct Problem {
+    line: usize,
+    column: usize,
+    msg: String,
+}
+
+struct UnknownKey {
+    cmd_name: String,
+    key: String,
+    line: usize,
+    column: usize,
+}
+
+struct Checker<'a> {
+    content: &'a str,
+    file_path: &'a Path,
+    format: Format,
+    lines: Map<String, usize>,
+    settings: Settings,
+    unknown_keys: Vec<UnknownKey>,
+    problems: Vec<Problem>,
+}
+
+impl<'a> Checker<'a> {
+    // returns the number of commands found
+    fn check(&mut self) -> usize {
+        let value: Value = match self.format.parse(self.content) {
+            Ok(v) => v,
+            Err((e, location)) => {
+                let (line, column) = location.unwrap_or((1, 1));
+                self.add(line, column, strip_location(&e));
+                return 0;
+            }
+        };
+        let mapping = match value {
+            Value::Mapping(m) => m,
+            _ => {
+                self.add(
+                    1,
+                    1,
+                    "invalid type: the file must contain a map of commands".to_string(),
+                );
+                return 0;
+            }
+        };
+
+        let mut commands: Map<String, Cmd> = Map::new();
+        for (k, v) in mapping {
+            let key = match k {
+                Value::String(s) => s,
+                _ => {
+                    self.add(1, 1, format!("invalid key {:?}, keys must be strings", k));
+                    continue;
+                }
+            };
+            if key == ".env" {
+                if let Err(e) = from_value::<Map<String, String>>(v) {
+                    let line = self.line(&key);
+                    self.add(line, 1, format!(".env: {}", e));
+                }
+            } else if key == ".settings" {
+                match from_value::<Settings>(v) {
+                    Ok(s) => self.settings = s,
+                    Err(e) => {
+                        let line = self.line(&key);
+                        self.add(line, 1, format!(".settings: {}", e));
+                    }
+                }
+            } else if let Some(cmd) = self.check_command(&key, v) {
+                commands.insert(key, cmd);
+            }
+        }
+
+        if let Some(shell) = &self.settings.shell {
+            if let Err(e) = set_shell(&mut commands, shell) {
+                let line = self.line(".settings");
+                self.add(line, 1, format!(".settings: {}", e));
+            }
+        }
+        if let Err(e) = interpreter::check_shell_options(&self.settings.shell_options) {
+            let line = self.line(".settings");
+            self.add(line, 1, format!(".settings: {}", e));
+        }
+
+        for clash in alias_clashes(&commands) {
+            let (line, column) = self.find_cmd_key(&clash.cmd_name, "aliases");
+            self.add(line, column, format!("{}.aliases: {}", clash.cmd_name, clash));
+        }
+        for (cmd_name, cmd) in &commands {
+            if let Err(e) = cmd.check_shell_options(&self.settings) {
+                let key = if cmd.strict.is_some() {
+                    "strict"
+                } else {
+                    "shell_options"
+                };
+                let (line, column) = self.find_cmd_key(cmd_name, key);
+                self.add(line, column, format!("{}: {}", cmd_name, e));
+            }
+            self.check_paths(cmd_name, cmd);
+            self.check_references(cmd_name, cmd, &commands);
+        }
+        self.check_cycles(&commands);
+
+        // reported last since .settings may come after the commands
+        if !self.settings.allow_unknown_keys {
+            let allowed = cmd_key_names();
+            for u in self.unknown_keys.split_off(0) {
+                let hint = match closest(&u.key, &allowed) {
+                    Some(c) => format!(", perhaps you meant \"{}\"?", c),
+                    None => "".to_string(),
+                };
+                self.add(
+                    u.line,
+                    u.column,
+                    format!("{}: unknown key \"{}\"{}", u.cmd_name, u.key, hint),
+                );
+            }
+        }
+        commands.len()
+    }
+
+    fn check_command(&mut self, cmd_name: &str, value: Value) -> Option<Cmd> {
+        let mut mapping = match value {
+            Value::Mapping(m) => m,
+            v => {
+                return match from_value::<Cmd>(v) {
+                    Ok(c) => Some(c),
+                    Err(e) => {
+                        let line = self.line(cmd_name);
+                        self.add(line, 1, format!("{}: {}", cmd_name, e));
+                        None
+                    }
+                };
+            }
+        };
+
+        let mut valid = true;
+        let run_key = Value::String("run".to_string());
+        let run = mapping
+            .get(&run_key)
+            .cloned()
+            .unwrap_or_else(|| Value::String(String::new()));
+        for (k, v) in mapping.clone() {
+            let key = match &k {
+                Value::String(s) => s.clone(),
+                _ => format!("{:?}", k),
+            };
+            let (line, column) = self.find_cmd_key(cmd_name, &key);
+            if !cmd_key_names().contains(&key) {
+                mapping.remove(&k);
+                self.unknown_keys.push(UnknownKey {
+                    cmd_name: cmd_name.to_string(),
+                    key,
+                    line,
+                    column,
+                });
+                continue;
+            }
+            // check each key on its own so every invalid value is reported with its location
+            let mut single = Mapping::new();
+            single.insert(run_key.clone(), run.clone());
+            single.insert(k, v);
+            if let Err(e) = from_value::<Cmd>(Value::Mapping(single)) {
+                self.add(line, column, format!("{}.{}: {}", cmd_name, key, e));
+                valid = false;
+            }
+        }
+        if !valid {
+            return None;
+        }
+        match from_value::<Cmd>(Value::Mapping(mapping)) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                let line = self.line(cmd_name);
+                self.add(line, 1, format!("{}: {}", cmd_name, e));
+                None
+            }
+        }
+    }
+
+    fn check_paths(&mut self, cmd_name: &str, cmd: &Cmd) {
+        let working_dir: Option<PathBuf> = match get_working_dir(cmd, self.file_path) {
+            Ok(wd) => Some(wd),
+            Err(e) => {
+                let (line, column) = self.find_cmd_key(cmd_name, "working_dir");
+                self.add(line, column, format!("{}.working_dir: {}", cmd_name, e));
+                None
+            }
+        };
+        if let (Some(wd), Some(watch)) = (working_dir, &cmd.watch) {
+            let mut p = PathBuf::from(watch);
+            if p.is_relative() {
+                p = wd.join(&p);
+            }
+            if !p.exists() {
+                let (line, column) = self.find_cmd_key(cmd_name, "watch");
+                self.add(
+                    line,
+                    column,
+                    format!("{}.watch: the watch directory \"{}\" does not exist", cmd_name, watch),
+                );
+            }
+        }
+    }
+
+    fn check_references(&mut self, cmd_name: &str, cmd: &Cmd, commands: &Map<String, Cmd>) {
+        for (r, sub_cmd_name) in cmd.references() {
+            let msg = match resolve(commands, &sub_cmd_name).map(|(_, c)| c) {
+                // the sub-command exists but is invalid, that problem has already been reported
+                None if self.lines.contains_key(&sub_cmd_name) => continue,
+                None => format!("{}: sub-command \"{}\" not found", cmd_name, sub_cmd_name),
+                Some(c) if r == INLINE_PREFIX && !c.inlinable_in(cmd) => format!(
+                    "{}: sub-command \"{}\" not a {} script, it can't be used inline with '{}'",
+                    cmd_name,
+                    sub_cmd_name,
+                    cmd.ex(),
+                    INLINE_PREFIX
+                ),
+                _ => continue,
+            };
+            let (line, column) = self.find_reference(cmd_name, r, &sub_cmd_name);
+            self.add(line, column, msg);
+        }
+    }
+
+    fn check_cycles(&mut self, commands: &Map<String, Cmd>) {
+        let mut done: HashMap<String, bool> = HashMap::new();
+        for cmd_name in commands.keys() {
+            let mut stack: Vec<(char, String)> = Vec::new();
+            self.visit(cmd_name, commands, &mut stack, &mut done);
+        }
+    }
+
+    fn visit(
+        &mut self,
+        cmd_name: &str,
+        commands: &Map<String, Cmd>,
+        stack: &mut Vec<(char, String)>,
+        done: &mut HashMap<String, bool>,
+    ) {
+        if done.contains_key(cmd_name) {
+            return;
+        }
+        let cmd = match commands.get(cmd_name) {
+            Some(c) => c,
+            None => return,
+        };
+        for (r, ref_name) in cmd.references() {
+            // aliases are followed using the real name of the command
+            let sub_cmd_name = match resolve(commands, &ref_name) {
+                Some((name, _)) => name.clone(),
+                None => ref_name.clone(),
+            };
+            if sub_cmd_name == cmd_name || stack.iter().any(|(_, c)| c == &sub_cmd_name) {
+                let start = stack
+                    .iter()
+                    .position(|(_, c)| c == &sub_cmd_name)
+                    .unwrap_or(stack.len());
+                let mut cycle: Vec<String> = stack[start..].iter().map(|(_, c)| c.clone()).collect();
+                cycle.push(cmd_name.to_string());
+                cycle.push(sub_cmd_name.clone());
+                let (line, column) = self.find_reference(cmd_name, r, &ref_name);
+                self.add(line, column, format!("recursion cycle: {}", cycle.join(" › ")));
+                continue;
+            }
+            stack.push((r, cmd_name.to_string()));
+            self.visit(&sub_cmd_name, commands, stack, done);
+            stack.pop();
+        }
+        done.insert(cmd_name.to_string(), true);
+    }
+
+    fn line(&self, cmd_name: &str) -> usize {
+        self.lines.get(cmd_name).cloned().unwrap_or(1)
+    }
+
+    // the lines belonging to a command's definition, starting with the line of its key
+    fn block(&self, cmd_name: &str) -> Vec<(usize, &'a str)> {
+        let start = self.line(cmd_name);
+        let end = self
+            .lines
+            .values()
+            .cloned()
+            .filter(|&l| l > start)
+            .min()
+            .unwrap_or(usize::MAX);
+        self.content
+            .lines()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l))
+            .filter(|&(i, _)| i >= start && i < end)
+            .collect()
+    }
+
+    fn find_cmd_key(&self, cmd_name: &str, key: &str) -> (usize, usize) {
+        for (i, l) in self.block(cmd_name).into_iter().skip(1) {
+            let trimmed = l.trim_start();
+            let trimmed = trimmed.trim_start_matches(['"', '\'']);
+            let rest = trimmed
+                .strip_prefix(key)
+                .map(|r| r.trim_start_matches(['"', '\'']).trim_start());
+            // "key:" in YAML and JSON, "key =" in TOML
+            if matches!(rest, Some(r) if r.starts_with(':') || r.starts_with('=')) {
+                return (i, l.len() - l.trim_start().len() + 1);
+            }
+        }
+        (self.line(cmd_name), 1)
+    }
+
+    fn find_reference(&self, cmd_name: &str, r: char, sub_cmd_name: &str) -> (usize, usize) {
+        let needle = format!("{}{}", r, sub_cmd_name);
+        for (i, l) in self.block(cmd_name) {
+            if let Some(col) = l.find(&needle) {
+                return (i, l[..col].chars().count() + 1);
+            }
+        }
+        (self.line(cmd_name), 1)
+    }
+
+    fn add(&mut self, line: usize, column: usize, msg: String) {
+        self.problems.push(Problem { line, column, msg });
+    }
+}
+
+fn strip_location(msg: &str) -> String {
+    lazy_static! {
+        static ref LOCATION: Regex = Regex::new(r" at line \d+ column \d+$").unwrap();
+    }
+    LOCATION.replace(msg, "").to_string()
+}
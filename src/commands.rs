@@ -1,14 +1,16 @@
 use std::env;
 use std::fmt;
-use std::fs::File;
+use std::fs;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 
 use linked_hash_map::LinkedHashMap as Map;
-use serde::de::{self, Deserialize, Deserializer, Error, SeqAccess, Visitor};
-use serde_yaml::{from_reader, from_value, Mapping, Value};
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer, Error, SeqAccess, Visitor};
+use serde_yaml::{from_value, Mapping, Value};
 
-use crate::utils::{BASH, BASH_SMART, DONKEY_FILE_ENV};
+use crate::interpreter::{self, Interpreter, SMART_SUFFIX};
+use crate::prepare::{DONK_PREFIX, INLINE_PREFIX, NO_ECHO_PREFIX};
+use crate::utils::{suggestion, BASH, DONKEY_FILE_ENV};
 
 #[derive(Debug, Deserialize)]
 pub struct FileConfig {
@@ -16,14 +18,228 @@ pub struct FileConfig {
     #[serde(default)]
     pub env: Map<String, String>,
 
+    #[serde(rename = ".settings")]
+    #[serde(default)]
+    pub settings: Settings,
+
     #[serde(flatten)]
     pub commands: Map<String, Cmd>,
+
+    // line number on which each command is defined, populated by load_file
+    #[serde(skip)]
+    pub lines: Map<String, usize>,
+
+    // content of the file, used to find where each line of a smart script came from, populated by load_file
+    #[serde(skip)]
+    pub source: String,
 }
 
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    #[serde(default)]
+    pub allow_unknown_keys: bool,
+    #[serde(default)]
+    pub prefix_matching: bool,
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub shell_options: Vec<String>,
+    #[serde(default)]
+    pub strict: bool,
+}
+
+// keys allowed in .settings
+pub const SETTINGS_KEYS: [Key; 5] = [
+    Key {
+        name: "allow_unknown_keys",
+        schema: r#"{"type": "boolean", "default": false}"#,
+        description:
+            "Don't fail on unknown keys in command definitions, eg. when a file is used by newer versions of donk",
+    },
+    Key {
+        name: "prefix_matching",
+        schema: r#"{"type": "boolean", "default": false}"#,
+        description: "Run a command given a unique prefix of its name or alias, eg. \"donk bu\" to run \"build\"",
+    },
+    Key {
+        name: "shell",
+        schema: r#"{"type": "string", "default": "bash"}"#,
+        description: "Shell used to run commands without \"ex:\", \"bash\", \"sh\", \"zsh\" or the path to one of them",
+    },
+    Key {
+        name: "shell_options",
+        schema: STRINGS,
+        description: "Options set with \"set -o\" at the start of smart scripts run by a shell, eg. \"xtrace\"",
+    },
+    Key {
+        name: "strict",
+        schema: r#"{"type": "boolean", "default": false}"#,
+        description: "Set the \"pipefail\" and \"nounset\" options in smart scripts run by a shell",
+    },
+];
+
 impl FileConfig {
     pub fn keys(&self) -> Vec<String> {
         self.commands.keys().cloned().collect()
     }
+
+    // find a command by its name or one of its aliases, returning the command's real name
+    pub fn get(&self, name: &str) -> Option<(&String, &Cmd)> {
+        resolve(&self.commands, name)
+    }
+
+    // real names of the commands with a name or alias starting with prefix
+    pub fn prefix_matches(&self, prefix: &str) -> Vec<&String> {
+        let mut matches: Vec<&String> = Vec::new();
+        for (name, cmd) in &self.commands {
+            if name.starts_with(prefix) || cmd.aliases.iter().any(|a| a.starts_with(prefix)) {
+                matches.push(name);
+            }
+        }
+        matches
+    }
+
+    // command names and aliases, used for completion and suggestions
+    pub fn names(&self, all: bool) -> Vec<String> {
+        let mut names = self.visible_keys(all);
+        for name in self.visible_keys(all) {
+            names.extend(self.commands[&name].aliases.iter().cloned());
+        }
+        names
+    }
+
+    // commands shown in the list of commands, hidden commands are only included if "all" is true
+    pub fn visible_keys(&self, all: bool) -> Vec<String> {
+        self.commands
+            .iter()
+            .filter(|(name, cmd)| all || !cmd.hidden(name))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    pub fn line(&self, cmd_name: &str) -> Option<usize> {
+        self.lines.get(cmd_name).cloned()
+    }
+
+    // use this shell for commands without "ex:", overriding .settings.shell when set by --shell
+    pub fn set_shell(&mut self, shell: &str) -> Result<(), String> {
+        set_shell(&mut self.commands, shell)
+    }
+
+    // called once the shell is known, after .settings.shell and --shell
+    pub fn check_shell_options(&self) -> Result<(), String> {
+        for (name, cmd) in &self.commands {
+            if let Err(e) = cmd.check_shell_options(&self.settings) {
+                let line = match self.line(name) {
+                    Some(l) => format!(" on line {}", l),
+                    None => "".to_string(),
+                };
+                return err!("command \"{}\"{}: {}", name, line, e);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_aliases(&self) -> Result<(), String> {
+        match alias_clashes(&self.commands).first() {
+            Some(clash) => {
+                let line = match self.line(&clash.cmd_name) {
+                    Some(l) => format!(" on line {}", l),
+                    None => "".to_string(),
+                };
+                err!("{}{}", clash, line)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn check_unknown_keys(&self) -> Result<(), String> {
+        let allowed = cmd_key_names();
+        for (cmd_name, cmd) in &self.commands {
+            if let Some(key) = cmd.unknown_keys.first() {
+                let line = match self.line(cmd_name) {
+                    Some(l) => format!(" on line {}", l),
+                    None => "".to_string(),
+                };
+                return err!(
+                    "unknown key \"{}\" in command \"{}\"{}, allowed keys are:\n  {}{}",
+                    key,
+                    cmd_name,
+                    line,
+                    allowed.join(", "),
+                    suggestion(key, &allowed)
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+// use this shell for commands without "ex:", also used by --check which builds the commands itself
+pub fn set_shell(commands: &mut Map<String, Cmd>, shell: &str) -> Result<(), String> {
+    interpreter::check_shell(shell)?;
+    for (_, cmd) in commands.iter_mut() {
+        cmd.shell = shell.to_string();
+    }
+    Ok(())
+}
+
+pub fn resolve<'a>(commands: &'a Map<String, Cmd>, name: &str) -> Option<(&'a String, &'a Cmd)> {
+    commands
+        .iter()
+        .find(|(cmd_name, _)| *cmd_name == name)
+        .or_else(|| commands.iter().find(|(_, cmd)| cmd.aliases.iter().any(|a| a == name)))
+}
+
+pub struct AliasClash {
+    pub cmd_name: String,
+    pub alias: String,
+    // the command with the same name, or which has the same alias
+    other: String,
+    other_is_alias: bool,
+}
+
+impl fmt::Display for AliasClash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.other_is_alias {
+            write!(
+                f,
+                "alias \"{}\" of command \"{}\" is also an alias of command \"{}\"",
+                self.alias, self.cmd_name, self.other
+            )
+        } else {
+            write!(
+                f,
+                "alias \"{}\" of command \"{}\" clashes with command \"{}\"",
+                self.alias, self.cmd_name, self.other
+            )
+        }
+    }
+}
+
+// aliases which are the name of a command or also an alias of an earlier command
+pub fn alias_clashes(commands: &Map<String, Cmd>) -> Vec<AliasClash> {
+    let mut seen: Map<&str, &str> = Map::new();
+    let mut clashes: Vec<AliasClash> = Vec::new();
+    for (cmd_name, cmd) in commands {
+        for alias in &cmd.aliases {
+            let (other, other_is_alias) = if commands.contains_key(alias) {
+                (alias.as_str(), false)
+            } else if let Some(other) = seen.get(alias.as_str()) {
+                (*other, true)
+            } else {
+                seen.insert(alias, cmd_name);
+                continue;
+            };
+            clashes.push(AliasClash {
+                cmd_name: cmd_name.clone(),
+                alias: alias.clone(),
+                other: other.to_string(),
+                other_is_alias,
+            });
+        }
+    }
+    clashes
 }
 
 #[derive(Debug)]
@@ -34,34 +250,142 @@ pub struct Cmd {
     pub working_dir: Option<String>,
     pub watch: Option<String>,
     pub watch_debounce: f32,
-    executable: String,
+    pub log: Option<String>,
+    pub log_keep: Option<u32>,
+    pub group: Option<String>,
+    pub aliases: Vec<String>,
+    pub complete: Option<Complete>,
+    pub container: Option<Container>,
+    pub shell_options: Vec<String>,
+    // None to use .settings.strict
+    pub strict: Option<bool>,
+    pub retries: u32,
+    // seconds before the first retry, multiplied by retry_backoff after each retry
+    pub retry_delay: f32,
+    pub retry_backoff: f32,
+    // exit codes which are retried, any failure is retried if empty
+    pub retry_on_exit_codes: Vec<i32>,
+    // shell used for smart scripts without "ex:", from .settings.shell or --shell
+    pub shell: String,
+    hidden: bool,
+    // "ex:", None for smart scripts run with the shell
+    executable: Option<String>,
     description: Option<String>,
+    unknown_keys: Vec<String>,
+}
+
+// candidates offered when completing the arguments of a command
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Complete {
+    // fixed values, eg. flags or choices
+    Values(Vec<String>),
+    // bash script printing one candidate per line, eg. a list of test files
+    Script(String),
+}
+
+fn dft_engine() -> String {
+    "docker".to_string()
+}
+
+// image to run the command in, the directory of the config file is mounted so the script is available
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Container {
+    pub image: String,
+    // "docker", "podman" or the path to either
+    #[serde(default = "dft_engine")]
+    pub engine: String,
+    // extra bind mounts as "host:container", relative host paths are relative to the config file
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    // where the directory of the config file is mounted, by default the same path as outside the container
+    pub workdir: Option<String>,
 }
 
 impl Cmd {
     pub fn smart(&self) -> bool {
-        match self.executable.as_ref() {
-            BASH_SMART => true,
-            _ => false,
+        self.shebang().is_none() && self.ex().ends_with(SMART_SUFFIX)
+    }
+
+    // interpreter from a "#!" line at the start of the script, eg. "/usr/bin/env python3", the script is then
+    // executed directly rather than with "ex:"
+    pub fn shebang(&self) -> Option<&str> {
+        self.run.first()?.lines().next()?.strip_prefix("#!").map(str::trim)
+    }
+
+    // "ex:" as written, eg. "python", or "<shell>-smart" by default
+    pub fn ex(&self) -> String {
+        match &self.executable {
+            Some(ex) => ex.clone(),
+            None => format!("{}{}", self.shell, SMART_SUFFIX),
         }
     }
 
+    // commands marked "hidden: true" or starting with "_" are omitted from the list of commands
+    pub fn hidden(&self, name: &str) -> bool {
+        self.hidden || name.starts_with('_')
+    }
+
     pub fn executable(&self) -> String {
-        if self.smart() {
-            BASH.to_string()
-        } else {
-            self.executable.clone()
+        match self.shebang() {
+            Some(shebang) => shebang.to_string(),
+            None => {
+                let ex = self.ex();
+                ex.strip_suffix(SMART_SUFFIX).unwrap_or(&ex).to_string()
+            }
         }
     }
 
+    // unknown executables are run with the path of the script as their only argument
+    pub fn interpreter(&self) -> Option<&'static Interpreter> {
+        match self.shebang() {
+            Some(shebang) => interpreter::from_shebang(shebang),
+            None => interpreter::find(&self.executable()),
+        }
+    }
+
+    // options set at the start of the script from .settings and the command, options of inline sub-commands
+    // are ignored as they share the script
+    pub fn shell_options(&self, settings: &Settings) -> Vec<String> {
+        let mut options: Vec<String> = Vec::new();
+        if self.strict(settings) {
+            options.extend(STRICT_OPTIONS.iter().map(ToString::to_string));
+        }
+        for option in settings.shell_options.iter().chain(&self.shell_options) {
+            if !options.contains(option) {
+                options.push(option.clone());
+            }
+        }
+        options
+    }
+
+    fn strict(&self, settings: &Settings) -> bool {
+        self.strict.unwrap_or(settings.strict)
+    }
+
+    // whether the shell running the script supports its options, eg. "sh" doesn't support "strict"
+    pub fn check_shell_options(&self, settings: &Settings) -> Result<(), String> {
+        match self.interpreter() {
+            Some(i) if self.smart() => i.check_options(&self.shell_options(settings), self.strict(settings)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn retry_on(&self, exit_code: i32) -> bool {
+        self.retry_on_exit_codes.is_empty() || self.retry_on_exit_codes.contains(&exit_code)
+    }
+
+    // whether this command can be included with "<" in the smart script of another command
+    pub fn inlinable_in(&self, parent: &Cmd) -> bool {
+        self.smart() && self.interpreter().map(|i| i.name) == parent.interpreter().map(|i| i.name)
+    }
+
     fn first_line(&self) -> String {
         match self.run.first() {
             Some(f) => {
                 let mut first_line = f.clone();
-                let mut more = match self.run.len() {
-                    c if c > 1 => true,
-                    _ => false,
-                };
+                let mut more = self.run.len() > 1;
                 if let Some(nl) = first_line.find('\n') {
                     more = true;
                     first_line = first_line[..nl].to_string();
@@ -85,8 +409,30 @@ impl Cmd {
         }
     }
 
+    // sub-commands referenced by "+" calls and "<" inline sub-commands in a smart command
+    pub fn references(&self) -> Vec<(char, String)> {
+        if !self.smart() {
+            return Vec::new();
+        }
+        self.run
+            .join("\n")
+            .split('\n')
+            .filter_map(|line| {
+                let line = line.strip_prefix(NO_ECHO_PREFIX).unwrap_or(line);
+                if let Some(call) = line.strip_prefix(DONK_PREFIX) {
+                    call.split_whitespace()
+                        .next()
+                        .map(|name| (DONK_PREFIX, name.to_string()))
+                } else {
+                    line.strip_prefix(INLINE_PREFIX)
+                        .map(|name| (INLINE_PREFIX, name.trim().to_string()))
+                }
+            })
+            .collect()
+    }
+
     pub fn summary(&self) -> String {
-        let ex_str = if self.smart() {
+        let ex_str = if self.executable.is_none() && self.shebang().is_none() {
             "".to_string()
         } else {
             format!("{}, ", self.executable())
@@ -99,15 +445,191 @@ impl Cmd {
     }
 }
 
-const PATH_OPTIONS: [&str; 6] = [
+pub struct Key {
+    pub name: &'static str,
+    // JSON Schema describing the value
+    pub schema: &'static str,
+    pub description: &'static str,
+}
+
+const STRING: &str = r#"{"type": "string"}"#;
+const STRINGS: &str = r#"{"type": "array", "items": {"type": "string"}}"#;
+const STRING_MAP: &str = r#"{"type": "object", "additionalProperties": {"type": "string"}}"#;
+const STRICT_OPTIONS: [&str; 2] = ["pipefail", "nounset"];
+pub const RUN_SCHEMA: &str = r#"{"oneOf": [{"type": "string"}, {"type": "array", "items": {"type": "string"}}]}"#;
+
+// keys allowed in a command definition, this is also used to generate the JSON Schema
+pub const CMD_KEYS: [Key; 21] = [
+    Key {
+        name: "run",
+        schema: RUN_SCHEMA,
+        description: "Line or lines of the script to run",
+    },
+    Key {
+        name: "args",
+        schema: STRINGS,
+        description: "Extra arguments passed to the script before those from the command line",
+    },
+    Key {
+        name: "env",
+        schema: STRING_MAP,
+        description: "Environment variables set when running the command",
+    },
+    Key {
+        name: "working_dir",
+        schema: STRING,
+        description: "Directory to run the command in, relative paths are relative to the config file",
+    },
+    Key {
+        name: "watch",
+        schema: STRING,
+        description: "Directory to watch, the command is restarted when files change",
+    },
+    Key {
+        name: "watch_debounce",
+        schema: r#"{"type": "number", "minimum": 0, "default": 0.2}"#,
+        description: "Time in seconds for which file changes are grouped together when watching",
+    },
+    Key {
+        name: "log",
+        schema: STRING,
+        description: "Directory to write timestamped log files of the command's output to",
+    },
+    Key {
+        name: "log_keep",
+        schema: r#"{"type": "integer", "minimum": 1}"#,
+        description: "Number of log files to keep, older files are deleted",
+    },
+    Key {
+        name: "ex",
+        schema: r#"{"type": "string", "default": "bash-smart"}"#,
+        description:
+            "Executable used to run the script, eg. \"python\" or \"python-smart\", by default a \"smart\" bash script",
+    },
+    Key {
+        name: "description",
+        schema: STRING,
+        description: "Description of the command shown in the list of commands",
+    },
+    Key {
+        name: "group",
+        schema: STRING,
+        description: "Heading under which the command is shown in the list of commands",
+    },
+    Key {
+        name: "hidden",
+        schema: r#"{"type": "boolean", "default": false}"#,
+        description: "Omit the command from the list of commands unless \"--all\" is used",
+    },
+    Key {
+        name: "aliases",
+        schema: STRINGS,
+        description: "Other names which can be used to run the command, eg. \"t\" for \"test\"",
+    },
+    Key {
+        name: "complete",
+        schema: RUN_SCHEMA,
+        description:
+            "Values offered when completing the command's arguments, or a bash script printing them one per line",
+    },
+    Key {
+        name: "container",
+        schema: r#"{"type": "object", "properties": {"image": {"type": "string"}, "engine": {"type": "string", "default": "docker"}, "volumes": {"type": "array", "items": {"type": "string"}}, "workdir": {"type": "string"}}, "required": ["image"], "additionalProperties": false}"#,
+        description: "Run the command in a docker or podman container with the directory of the config file mounted",
+    },
+    Key {
+        name: "shell_options",
+        schema: STRINGS,
+        description: "Options set with \"set -o\" at the start of the script, added to \".settings.shell_options\"",
+    },
+    Key {
+        name: "strict",
+        schema: r#"{"type": "boolean"}"#,
+        description: "Set the \"pipefail\" and \"nounset\" shell options, overrides \".settings.strict\"",
+    },
+    Key {
+        name: "retries",
+        schema: r#"{"type": "integer", "minimum": 0, "default": 0}"#,
+        description:
+            "Number of times to run the command again if it fails, attempts share one log file, not used with \"watch\"",
+    },
+    Key {
+        name: "retry_delay",
+        schema: r#"{"type": "number", "minimum": 0, "default": 1}"#,
+        description: "Seconds to wait before retrying the command, delays are capped at an hour",
+    },
+    Key {
+        name: "retry_backoff",
+        schema: r#"{"type": "number", "minimum": 1, "default": 1}"#,
+        description: "Factor the delay is multiplied by after each retry, eg. 2 to double it",
+    },
+    Key {
+        name: "retry_on_exit_codes",
+        schema: r#"{"type": "array", "items": {"type": "integer"}}"#,
+        description: "Only retry the command when it fails with one of these exit codes",
+    },
+];
+
+pub fn cmd_key_names() -> Vec<String> {
+    CMD_KEYS.iter().map(|k| k.name.to_string()).collect()
+}
+
+const PATH_OPTIONS: [&str; 8] = [
     "donk.yml",
     "donk.yaml",
     "donkey.yml",
     "donkey.yaml",
     "donkey-make.yml",
     "donkey-make.yaml",
+    "donk.toml",
+    "donk.json",
 ];
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Format::Toml,
+            Some("json") => Format::Json,
+            _ => Format::Yaml,
+        }
+    }
+
+    // deserialize content, errors include the line and column of the problem if known
+    pub fn parse<T: DeserializeOwned>(self, content: &str) -> Result<T, (String, Option<(usize, usize)>)> {
+        match self {
+            Format::Yaml => {
+                serde_yaml::from_str(content).map_err(|e| (e.to_string(), e.location().map(|l| (l.line(), l.column()))))
+            }
+            Format::Toml => {
+                toml::from_str(content).map_err(|e| (e.to_string(), e.line_col().map(|(l, c)| (l + 1, c + 1))))
+            }
+            Format::Json => serde_json::from_str(content).map_err(|e| {
+                let location = match e.line() {
+                    0 => None,
+                    l => Some((l, e.column())),
+                };
+                (e.to_string(), location)
+            }),
+        }
+    }
+
+    // find the line numbers of top level keys, none of the deserializers expose positions
+    pub fn find_keys(self, content: &str) -> Map<String, usize> {
+        match self {
+            Format::Yaml => find_yaml_keys(content),
+            Format::Toml => find_toml_keys(content),
+            Format::Json => find_json_keys(content),
+        }
+    }
+}
+
 pub fn find_file(file_path_opt: &Option<String>) -> Result<PathBuf, String> {
     if let Some(file_path) = file_path_opt {
         return Ok(PathBuf::from(file_path.clone()));
@@ -123,24 +645,194 @@ pub fn find_file(file_path_opt: &Option<String>) -> Result<PathBuf, String> {
     }
     err!(
         "No commands config file provided, and no default found, tried:\n  \
-         donk.ya?ml, donkey.ya?ml and donkey-make.ya?ml"
+         donk.ya?ml, donkey.ya?ml, donkey-make.ya?ml, donk.toml and donk.json"
     )
 }
 
 pub fn load_file(path: &PathBuf) -> Result<FileConfig, String> {
-    let file = match File::open(&path) {
+    let content = match fs::read_to_string(path) {
         Ok(t) => t,
         Err(e) => {
             return err!("Error opening {}:\n  {}", path.display(), e);
         }
     };
 
-    Ok(match from_reader(file) {
+    let format = Format::from_path(path);
+    let mut config: FileConfig = match format.parse(&content) {
         Ok(t) => t,
-        Err(e) => {
+        Err((e, _)) => {
             return err!("Error parsing {}:\n  {}", path.display(), e);
         }
-    })
+    };
+    config.lines = format.find_keys(&content);
+    config.source = content;
+    if let Some(shell) = config.settings.shell.clone() {
+        if let Err(e) = config.set_shell(&shell) {
+            return err!("Error parsing {}:\n  .settings: {}", path.display(), e);
+        }
+    }
+    if let Err(e) = interpreter::check_shell_options(&config.settings.shell_options) {
+        return err!("Error parsing {}:\n  .settings: {}", path.display(), e);
+    }
+    if let Err(e) = config.check_shell_options() {
+        return err!("Error parsing {}:\n  {}", path.display(), e);
+    }
+    if let Err(e) = config.check_aliases() {
+        return err!("Error parsing {}:\n  {}", path.display(), e);
+    }
+    if !config.settings.allow_unknown_keys {
+        if let Err(e) = config.check_unknown_keys() {
+            return err!("Error parsing {}:\n  {}", path.display(), e);
+        }
+    }
+    Ok(config)
+}
+
+fn find_yaml_keys(content: &str) -> Map<String, usize> {
+    let mut keys: Map<String, usize> = Map::new();
+    let mut base_indent: Option<usize> = None;
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("---") {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let base = *base_indent.get_or_insert(indent);
+        if indent != base {
+            continue;
+        }
+        let key = match trimmed.chars().next() {
+            Some(q) if q == '"' || q == '\'' => trimmed[1..].find(q).map(|end| &trimmed[1..=end]),
+            _ => trimmed.find(':').map(|end| trimmed[..end].trim_end()),
+        };
+        if let Some(k) = key {
+            keys.insert(k.to_string(), index + 1);
+        }
+    }
+    keys
+}
+
+fn find_toml_keys(content: &str) -> Map<String, usize> {
+    let mut keys: Map<String, usize> = Map::new();
+    let mut in_table = false;
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let key = if trimmed.starts_with("[[") {
+            in_table = true;
+            None
+        } else if let Some(header) = trimmed.strip_prefix('[') {
+            // sub-tables like [foo.env] belong to the first table, so take the first part of the name
+            in_table = true;
+            match header.chars().next() {
+                Some(q) if q == '"' || q == '\'' => header[1..].find(q).map(|end| &header[1..=end]),
+                _ => header.find(['.', ']']).map(|end| header[..end].trim()),
+            }
+        } else if in_table {
+            None
+        } else {
+            trimmed
+                .find('=')
+                .map(|end| trimmed[..end].trim().trim_matches(['"', '\'']))
+        };
+        if let Some(k) = key {
+            if !keys.contains_key(k) {
+                keys.insert(k.to_string(), index + 1);
+            }
+        }
+    }
+    keys
+}
+
+fn find_json_keys(content: &str) -> Map<String, usize> {
+    let mut keys: Map<String, usize> = Map::new();
+    let mut depth = 0;
+    let mut line = 1;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            '"' => {
+                let key_line = line;
+                let mut key = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => key.extend(chars.next()),
+                        c => key.push(c),
+                    }
+                }
+                while let Some(&c) = chars.peek() {
+                    if !c.is_whitespace() {
+                        break;
+                    }
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    chars.next();
+                }
+                if depth == 1 && chars.peek() == Some(&':') && !keys.contains_key(&key) {
+                    keys.insert(key, key_line);
+                }
+            }
+            _ => (),
+        }
+    }
+    keys
+}
+
+fn dft_debounce() -> f32 {
+    0.2
+}
+
+fn dft_retry_delay() -> f32 {
+    1.0
+}
+
+fn dft_retry_backoff() -> f32 {
+    1.0
+}
+
+// the keys of a command definition, CMD_KEYS must list the same names
+#[derive(Debug, Deserialize)]
+struct Command {
+    #[serde(deserialize_with = "seq_or_string")]
+    run: Vec<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: Map<String, String>,
+    working_dir: Option<String>,
+    watch: Option<String>,
+    #[serde(default = "dft_debounce")]
+    pub watch_debounce: f32,
+    log: Option<String>,
+    log_keep: Option<u32>,
+    #[serde(rename = "ex")]
+    executable: Option<String>,
+    description: Option<String>,
+    group: Option<String>,
+    #[serde(default)]
+    hidden: bool,
+    #[serde(default)]
+    aliases: Vec<String>,
+    complete: Option<Complete>,
+    container: Option<Container>,
+    #[serde(default)]
+    shell_options: Vec<String>,
+    strict: Option<bool>,
+    #[serde(default)]
+    retries: u32,
+    #[serde(default = "dft_retry_delay")]
+    retry_delay: f32,
+    #[serde(default = "dft_retry_backoff")]
+    retry_backoff: f32,
+    #[serde(default)]
+    retry_on_exit_codes: Vec<i32>,
 }
 
 impl<'de> Deserialize<'de> for Cmd {
@@ -148,31 +840,6 @@ impl<'de> Deserialize<'de> for Cmd {
     where
         D: Deserializer<'de>,
     {
-        fn dft_exe() -> String {
-            BASH_SMART.to_string()
-        }
-        fn dft_debounce() -> f32 {
-            0.2
-        }
-
-        #[derive(Debug, Deserialize)]
-        struct Command {
-            #[serde(deserialize_with = "seq_or_string")]
-            run: Vec<String>,
-            #[serde(default)]
-            args: Vec<String>,
-            #[serde(default)]
-            env: Map<String, String>,
-            working_dir: Option<String>,
-            watch: Option<String>,
-            #[serde(default = "dft_debounce")]
-            pub watch_debounce: f32,
-            #[serde(rename = "ex")]
-            #[serde(default = "dft_exe")]
-            executable: String,
-            description: Option<String>,
-        }
-
         let mut v: Value = Deserialize::deserialize(deserializer)?;
         if v.is_string() || v.is_sequence() {
             let mut m: Mapping = Mapping::with_capacity(1);
@@ -180,11 +847,54 @@ impl<'de> Deserialize<'de> for Cmd {
             v = Value::Mapping(m);
         }
 
-        if v.is_mapping() {
+        if let Value::Mapping(m) = &v {
+            // unknown keys are checked by load_file so they can be allowed via .settings
+            let unknown_keys: Vec<String> = m
+                .iter()
+                .map(|(k, _)| match k {
+                    Value::String(s) => s.clone(),
+                    _ => format!("{:?}", k),
+                })
+                .filter(|k| !CMD_KEYS.iter().any(|key| key.name == k))
+                .collect();
             let c: Command = from_value(v).map_err(D::Error::custom)?;
             if c.watch_debounce < 0.0 {
                 return Err(D::Error::custom("watch_debounce must be greater than or equal to 0"));
             }
+            if c.log_keep == Some(0) {
+                return Err(D::Error::custom("log_keep must be greater than 0"));
+            }
+            if !c.retry_delay.is_finite() || c.retry_delay < 0.0 {
+                return Err(D::Error::custom(
+                    "retry_delay must be a number greater than or equal to 0",
+                ));
+            }
+            if !c.retry_backoff.is_finite() || c.retry_backoff < 1.0 {
+                return Err(D::Error::custom(
+                    "retry_backoff must be a number greater than or equal to 1",
+                ));
+            }
+            if let Some(ex) = c.executable.as_ref().and_then(|e| e.strip_suffix(SMART_SUFFIX)) {
+                if interpreter::find(ex).is_none() {
+                    return Err(D::Error::custom(format!(
+                        "smart scripts are not supported for \"{}\", interpreters supported are: {}",
+                        ex,
+                        interpreter::names().join(", ")
+                    )));
+                }
+            }
+            interpreter::check_shell_options(&c.shell_options).map_err(D::Error::custom)?;
+            if !c.shell_options.is_empty() || c.strict == Some(true) {
+                let shell = match &c.executable {
+                    Some(ex) => ex.strip_suffix(SMART_SUFFIX).and_then(interpreter::find),
+                    None => interpreter::find(BASH),
+                };
+                if !matches!(shell, Some(i) if i.shell()) {
+                    return Err(D::Error::custom(
+                        "shell_options and strict can only be used with smart scripts run by a shell",
+                    ));
+                }
+            }
             Ok(Cmd {
                 run: c.run,
                 args: c.args,
@@ -192,8 +902,23 @@ impl<'de> Deserialize<'de> for Cmd {
                 working_dir: c.working_dir,
                 watch: c.watch,
                 watch_debounce: c.watch_debounce,
+                log: c.log,
+                log_keep: c.log_keep,
+                group: c.group,
+                aliases: c.aliases,
+                complete: c.complete,
+                container: c.container,
+                shell_options: c.shell_options,
+                strict: c.strict,
+                retries: c.retries,
+                retry_delay: c.retry_delay,
+                retry_backoff: c.retry_backoff,
+                retry_on_exit_codes: c.retry_on_exit_codes,
+                hidden: c.hidden,
                 executable: c.executable,
+                shell: BASH.to_string(),
                 description: c.description,
+                unknown_keys,
             })
         } else {
             Err(D::Error::custom(
@@ -247,3 +972,78 @@ where
 
     deserializer.deserialize_any(StringOrSeq(PhantomData))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_keys_lines() {
+        let content = "# comment\nfoo:\n  run: x\n\n'bar baz': y\n\"q\": [a]\n";
+        assert_eq!(
+            format!("{:?}", Format::Yaml.find_keys(content)),
+            r#"{"foo": 2, "bar baz": 5, "q": 6}"#
+        );
+    }
+
+    #[test]
+    fn find_keys_indented() {
+        let content = "\n    foo:\n    - echo foo\n    bar: echo\n";
+        assert_eq!(
+            format!("{:?}", Format::Yaml.find_keys(content)),
+            r#"{"foo": 2, "bar": 4}"#
+        );
+    }
+
+    #[test]
+    fn find_keys_toml() {
+        let content = "\".env\" = {a = \"b\"}\n\n[foo]\nrun = \"x\"\n[foo.env]\nc = \"d\"\n[\"bar.baz\"]\nrun = []\n";
+        assert_eq!(
+            format!("{:?}", Format::Toml.find_keys(content)),
+            r#"{".env": 1, "foo": 3, "bar.baz": 7}"#
+        );
+    }
+
+    #[test]
+    fn find_keys_json() {
+        let content = "{\n  \"foo\": \"x\",\n  \"bar\": {\n    \"run\": [\"a\\\"b\"]\n  },\n  \"spam\"\n  : \"y\"\n}\n";
+        assert_eq!(
+            format!("{:?}", Format::Json.find_keys(content)),
+            r#"{"foo": 2, "bar": 3, "spam": 6}"#
+        );
+    }
+
+    // records the field names of a struct deriving Deserialize
+    struct FieldNames<'a>(&'a mut Vec<&'static str>);
+
+    impl<'de, 'a> Deserializer<'de> for FieldNames<'a> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            self.0.extend(fields);
+            Err(Error::custom("only the fields are required"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+            unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    #[test]
+    fn cmd_keys_match_fields() {
+        let mut fields = Vec::new();
+        assert!(Command::deserialize(FieldNames(&mut fields)).is_err());
+        let keys: Vec<&str> = CMD_KEYS.iter().map(|k| k.name).collect();
+        assert_eq!(keys, fields);
+    }
+}
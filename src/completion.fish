@@ -0,0 +1,46 @@
+# donk fish-completion script
+
+# to install fish completion for donk, add the following to ~/.config/fish/config.fish or equivalent:
+#     donk --completion-script fish | source
+
+# options which take a value, these are completed separately
+set -g __donk_value_options -f --file -w --watch --log-dir --format
+
+# donk finds the config file and works out whether a command or its arguments are being completed,
+# commands are described as "<command>\t<description>" which fish displays with the description
+function __donk_complete
+    # quoted so an empty current token is kept
+    set -l current (commandline -ct)
+    set -l tokens (commandline -opc) "$current"
+    set -l count (count $tokens)
+    if test $count -gt 1; and contains -- $tokens[-2] $__donk_value_options
+        return
+    end
+    set -l candidates (donk --complete --described (math $count - 1) -- $tokens 2>/dev/null)
+    if test (count $candidates) -gt 0
+        printf '%s\n' $candidates
+    else
+        __fish_complete_path "$current"
+    end
+end
+
+complete -c donk -f
+complete -c donk -a '(__donk_complete)'
+
+complete -c donk -s h -l help -d 'Print help information'
+complete -c donk -s V -l version -d 'Print version information'
+complete -c donk -s f -l file -r -F -d 'File in which to find commands'
+complete -c donk -s w -l watch -x -a '(__fish_complete_directories)' -d 'Watch this directory and restart the command when files change'
+complete -c donk -l log-dir -x -a '(__fish_complete_directories)' -d 'Write the output of the command to a log file in this directory'
+complete -c donk -s k -l keep-tmp-file -d 'Keep the temporary files upon completion'
+complete -c donk -s p -l prefix-output -d 'Prefix every line of output with the path of the command'
+complete -c donk -s q -l quiet -d 'Do not echo lines or print summaries'
+complete -c donk -s v -l verbose -d 'Print more information, use twice for even more'
+complete -c donk -l dry-run -d 'Print the generated script and exit without running it'
+complete -c donk -l explain -d 'Print the tree of sub-commands and exit'
+complete -c donk -l check -d 'Validate the config file and exit'
+complete -c donk -l schema -d 'Print the JSON Schema for config files and exit'
+complete -c donk -l list -d 'Print the commands available and exit'
+complete -c donk -l format -x -a 'plain json yaml' -d 'Output format for --list'
+complete -c donk -s a -l all -d 'Include hidden commands in the list of commands'
+complete -c donk -l keep-going -d 'Continue after a command fails when running multiple commands'
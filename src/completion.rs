@@ -1,18 +1,35 @@
 use std::env;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
-use crate::commands;
+use crate::commands::{self, Cmd, Complete, FileConfig};
+use crate::prepare::get_working_dir;
 
 pub const COMPLETION_SCRIPT: &str = "--completion-script";
+// used by completion scripts, followed by "[--described] <index of the word being completed> -- <words...>"
+pub const COMPLETE: &str = "--complete";
+const DESCRIBED: &str = "--described";
+// used by bash completion scripts from before "--complete" existed
 pub const COMPLETE_COMMAND: &str = "--complete-command";
 
+// options which take a value, these must match cli.yaml
+const FILE_OPTIONS: [&str; 2] = ["-f", "--file"];
+const VALUE_OPTIONS: [&str; 5] = ["-w", "--watch", "--log-dir", "--format", COMPLETION_SCRIPT];
+const COMMAND_SEP: &str = "+";
+
 #[allow(unused_must_use)]
 pub fn main() -> bool {
     let args: Vec<String> = env::args().collect();
-    if args.contains(&COMPLETION_SCRIPT.to_string()) {
-        print!("{}", include_str!("completion.sh"));
+    if let Some(i) = args.iter().position(|a| a == COMPLETION_SCRIPT) {
+        match args.get(i + 1).map(String::as_str) {
+            Some("zsh") => print!("{}", include_str!("completion.zsh")),
+            Some("fish") => print!("{}", include_str!("completion.fish")),
+            _ => print!("{}", include_str!("completion.sh")),
+        }
+    } else if args.get(1).map(String::as_str) == Some(COMPLETE) {
+        // errors in complete and complete_command are ignored, we just don't make any suggestions
+        complete(&args[2..]);
     } else if args.contains(&COMPLETE_COMMAND.to_string()) {
-        // errors in complete_command are ignored, we just don't make any suggestions
         complete_command(args);
     } else {
         return false;
@@ -21,21 +38,188 @@ pub fn main() -> bool {
 }
 
 fn complete_command(args: Vec<String>) -> Result<(), String> {
-    let config = if args.iter().count() == 3 && Path::new(&args[2]).is_file() {
-        let file_path = PathBuf::from(args[2].clone());
-        match commands::load_file(&file_path) {
-            Err(_) => default_config()?,
-            Ok(c) => c,
-        }
-    } else {
-        default_config()?
+    let file_path = match args.as_slice() {
+        [_, _, path] if Path::new(path).is_file() => Some(path.clone()),
+        _ => None,
     };
+    let (_, config) = load_config(&file_path)?;
+    println!("{}", config.names(false).join(" "));
+    Ok(())
+}
+
+// what is being completed, found by reading the command line the same way as parse_args
+#[derive(Debug, PartialEq)]
+enum Position {
+    Command,
+    // arguments of the command
+    Args(String),
+    // options and their values are completed by the shell
+    Other,
+}
 
-    println!("{}", config.keys().join(" "));
+#[derive(Debug, PartialEq)]
+struct Line {
+    file_path: Option<String>,
+    position: Position,
+}
+
+fn complete(args: &[String]) -> Result<(), String> {
+    let (described, args) = match args.first() {
+        Some(a) if a == DESCRIBED => (true, &args[1..]),
+        _ => (false, args),
+    };
+    let (index, words) = match args {
+        [index, sep, words @ ..] if sep == "--" => match index.parse::<usize>() {
+            Ok(i) => (i, words),
+            Err(_) => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    let line = parse_line(words, index);
+    let (file_path, config) = load_config(&line.file_path)?;
+    match line.position {
+        Position::Command => {
+            for name in config.names(false) {
+                match config.get(&name) {
+                    Some((_, cmd)) if described => println!("{}\t{}", name, cmd.description()),
+                    _ => println!("{}", name),
+                }
+            }
+        }
+        Position::Args(cmd_name) => {
+            if let Some((_, cmd)) = config.get(&cmd_name) {
+                print!("{}", complete_args(&config, cmd, &file_path)?);
+            }
+        }
+        Position::Other => (),
+    }
     Ok(())
 }
 
-fn default_config() -> Result<commands::FileConfig, String> {
-    let file_path = commands::find_file(&None)?;
-    commands::load_file(&file_path)
+fn parse_line(words: &[String], index: usize) -> Line {
+    let mut file_path: Option<String> = None;
+    let mut cli_file_path: Option<String> = None;
+    // the first word is the executable, unless it's a config file with a shebang line, eg. "./donk.yml"
+    if let Some(first) = words.first() {
+        if first.contains('/') && Path::new(first).is_file() && commands::load_file(&PathBuf::from(first)).is_ok() {
+            file_path = Some(first.clone());
+        }
+    }
+    let mut command: Option<String> = None;
+    let mut dash_dash = false;
+    let mut position = Position::Command;
+    let mut i = 1;
+    while i < index.min(words.len()) {
+        let word = &words[i];
+        if dash_dash {
+            // after "--" everything is an argument
+        } else if word == "--" {
+            dash_dash = true;
+        } else if FILE_OPTIONS.contains(&word.as_str()) {
+            i += 1;
+            cli_file_path = words.get(i).cloned();
+            if i == index {
+                position = Position::Other;
+            }
+        } else if let Some(path) = word.strip_prefix("--file=") {
+            cli_file_path = Some(path.to_string());
+        } else if VALUE_OPTIONS.contains(&word.as_str()) {
+            i += 1;
+            if i == index {
+                position = Position::Other;
+            }
+        } else if word == COMMAND_SEP && command.is_some() {
+            command = None;
+        } else if word.starts_with('-') {
+            // flags take no value
+        } else if command.is_none() && word.starts_with("./") && file_path.is_none() {
+            // donk used in the shebang line, the first argument is the path to the file
+            file_path = Some(word.clone());
+        } else if command.is_none() {
+            command = Some(word.clone());
+        }
+        i += 1;
+    }
+    if position != Position::Other {
+        position = match command {
+            Some(c) => Position::Args(c),
+            None => Position::Command,
+        };
+    }
+    Line {
+        // "-f" takes precedence over the shebang file, as in parse_args
+        file_path: cli_file_path.or(file_path),
+        position,
+    }
+}
+
+// candidates for the arguments of a command, one per line, from its "complete:" key
+fn complete_args(config: &FileConfig, cmd: &Cmd, file_path: &Path) -> Result<String, String> {
+    Ok(match &cmd.complete {
+        Some(Complete::Values(values)) => values.iter().map(|v| format!("{}\n", v)).collect(),
+        Some(Complete::Script(script)) => {
+            let output = Command::new(&cmd.shell)
+                .arg("-c")
+                .arg(script)
+                .envs(&config.env)
+                .envs(&cmd.env)
+                .current_dir(get_working_dir(cmd, file_path)?)
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+                .map_err(|e| e.to_string())?;
+            if output.status.success() {
+                String::from_utf8_lossy(&output.stdout).to_string()
+            } else {
+                String::new()
+            }
+        }
+        None => String::new(),
+    })
+}
+
+// find and load the config file exactly as when running a command
+fn load_config(file_path: &Option<String>) -> Result<(PathBuf, FileConfig), String> {
+    let path = commands::find_file(file_path)?;
+    let config = commands::load_file(&path)?;
+    Ok((path, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Line {
+        let words: Vec<String> = line.split(' ').map(ToString::to_string).collect();
+        parse_line(&words, words.len() - 1)
+    }
+
+    #[test]
+    fn parse_line_positions() {
+        assert_eq!(parse("donk ").position, Position::Command);
+        assert_eq!(parse("donk -v te").position, Position::Command);
+        assert_eq!(parse("donk test ").position, Position::Args("test".to_string()));
+        assert_eq!(
+            parse("donk -w x test -- foo ").position,
+            Position::Args("test".to_string())
+        );
+        assert_eq!(parse("donk lint + ").position, Position::Command);
+        assert_eq!(parse("donk --format ").position, Position::Other);
+    }
+
+    #[test]
+    fn parse_line_files() {
+        assert_eq!(parse("donk -f other.yml ").file_path, Some("other.yml".to_string()));
+        assert_eq!(
+            parse("donk --file=other.yml test ").file_path,
+            Some("other.yml".to_string())
+        );
+        let line = parse("donk ./script.yml ");
+        assert_eq!(line.file_path, Some("./script.yml".to_string()));
+        assert_eq!(line.position, Position::Command);
+        assert_eq!(
+            parse("donk ./script.yml -f x.yml t ").file_path,
+            Some("x.yml".to_string())
+        );
+    }
 }
@@ -13,18 +13,32 @@ _donk()
       return
       ;;
     -f|--file)
-      _filedir y*ml
+      _filedir '@(y*ml|toml|json)'
+      return
+      ;;
+    -w|--watch|--log-dir)
+      _filedir -d
+      return
+      ;;
+    --format)
+      COMPREPLY=($(compgen -W "plain json yaml" -- "$cur"))
       return
       ;;
   esac
 
   $split && return
 
+  # donk finds the config file and works out whether a command or its arguments are being completed
+  local candidates
+  candidates=$(donk --complete $cword -- "${words[@]}" 2>/dev/null)
   if [[ $cur == -* ]]; then
     # --completion-script is not added here, since completion must already be installed to be running this
-    COMPREPLY=($(compgen -W "-f --file -k --keep-tmp-file --help --version" -- "$cur" ))
-  elif hash donk; then
-    COMPREPLY=($(compgen -W "$(donk --complete-command $prev)" -- "$cur"))
+    COMPREPLY=($(compgen -W "$candidates -f --file -w --watch -k --keep-tmp-file -p --prefix-output -q --quiet
+      -v --verbose --log-dir --dry-run --explain --check --schema --list --format -a --all --keep-going
+      --help --version" -- "$cur" ))
+  else
+    COMPREPLY=($(compgen -W "$candidates" -- "$cur"))
+    [[ ${#COMPREPLY[@]} == 0 ]] && _filedir
   fi
 } &&
 complete -F _donk donk
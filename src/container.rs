@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use linked_hash_map::LinkedHashMap as Map;
+
+use crate::commands::Container;
+use crate::execute::Run;
+
+// "docker run" (or podman) running the script in the container, the script is written to the working directory
+// which must be inside the mounted directory of the config file
+pub fn command(
+    container: &Container,
+    executable: &str,
+    run: &Run,
+    envs: &Map<String, String>,
+    tty: bool,
+) -> Result<Command, String> {
+    let project_dir = canonical(&run.file_path)?
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| format!("\"{}\" appears to have no parent directory", run.file_path.display()))?;
+    let mount = match &container.workdir {
+        Some(w) => PathBuf::from(w),
+        None => project_dir.clone(),
+    };
+    let working_dir = canonical(&run.working_dir)?;
+    let container_dir = match working_dir.strip_prefix(&project_dir) {
+        Ok(rel) if rel.as_os_str().is_empty() => mount.clone(),
+        Ok(rel) => mount.join(rel),
+        Err(_) => {
+            return err!(
+                "The working directory \"{}\" must be inside \"{}\" to run in a container",
+                working_dir.display(),
+                project_dir.display()
+            )
+        }
+    };
+
+    let mut c = Command::new(&container.engine);
+    c.args(["run", "--rm", "--interactive", "--init"]);
+    if tty {
+        c.arg("--tty");
+    }
+    c.arg("--volume")
+        .arg(format!("{}:{}", project_dir.display(), mount.display()));
+    for volume in &container.volumes {
+        c.arg("--volume").arg(volume_arg(volume, &project_dir));
+    }
+    c.arg("--workdir").arg(&container_dir);
+    // values are read from the environment of the engine so they don't appear in the process list
+    for key in envs.keys() {
+        c.arg("--env").arg(key);
+    }
+    c.arg(&container.image).arg(executable).args(&run.args).envs(envs);
+    Ok(c)
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, String> {
+    path.canonicalize()
+        .map_err(|e| format!("unable to resolve \"{}\": {}", path.display(), e))
+}
+
+fn volume_arg(volume: &str, project_dir: &Path) -> String {
+    match volume.split_once(':') {
+        Some((host, rest)) if host.starts_with('.') => {
+            let host = host.strip_prefix("./").unwrap_or(host);
+            format!("{}:{}", project_dir.join(host).display(), rest)
+        }
+        _ => volume.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_volumes() {
+        let project = Path::new("/home/x/project");
+        assert_eq!(volume_arg("./data:/data", project), "/home/x/project/data:/data");
+        assert_eq!(volume_arg("/cache:/root/.cache:ro", project), "/cache:/root/.cache:ro");
+        assert_eq!(volume_arg("named:/data", project), "named:/data");
+    }
+}
@@ -1,11 +1,11 @@
 use std::fs;
 use std::io::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::channel;
 use std::sync::Arc;
-use std::thread::{spawn, JoinHandle};
+use std::thread::{sleep, spawn, JoinHandle};
 use std::time::{Duration, Instant};
 
 use ansi_term::Colour::{Green, Yellow};
@@ -16,36 +16,114 @@ use notify::{op, raw_watcher, RawEvent, RecursiveMode, Watcher};
 use regex::Regex;
 
 use crate::commands::Cmd;
+use crate::container;
+use crate::output::{open_log, Capture, Log, LogFile};
 use crate::utils::{full_path, CliArgs};
 
 pub struct Run {
     pub cmd_name: String,
+    // the interpreter, or the script itself if it starts with "#!"
+    pub executable: String,
     pub args: Vec<String>,
     pub env: Map<String, String>,
     pub working_dir: PathBuf,
     pub tmp_path: PathBuf,
     pub file_path: PathBuf,
     pub watch_path: Option<PathBuf>,
+    pub log: Option<Log>,
+    pub prefix: Option<String>,
     pub print_summary: bool,
 }
 
 pub fn main(run: &Run, cmd: &Cmd, cli: &CliArgs) -> Result<i32, String> {
     let exit_code = match &run.watch_path {
-        Some(_) => run_command_watch(&run, &cmd),
-        None => run_command_once(&run, &cmd),
+        Some(_) => run_command_watch(run, cmd),
+        None => run_command_once(run, cmd),
     };
     delete(&run.tmp_path, cli.keep_tmp)?;
     match exit_code {
         Ok(t) => Ok(t),
         Err(e) => err!(
             "failed to execute command \"{} {}\": {}",
-            cmd.executable(),
+            run.executable,
             run.args.join(" "),
             e
         ),
     }
 }
 
+// single summary for several commands run one after the other
+pub struct Sequence {
+    names: Vec<String>,
+    file_path: PathBuf,
+    start: Instant,
+    // set when the first command starts, None for --dry-run where nothing is run
+    pub print_summary: Option<bool>,
+    done: Vec<(String, i32)>,
+}
+
+impl Sequence {
+    pub fn new(names: &[&String], file_path: &Path) -> Sequence {
+        Sequence {
+            names: names.iter().map(|n| n.to_string()).collect(),
+            file_path: file_path.to_path_buf(),
+            start: Instant::now(),
+            print_summary: None,
+            done: Vec::new(),
+        }
+    }
+
+    pub fn start(&mut self, print_summary: bool) {
+        self.print_summary = Some(print_summary);
+        self.start = Instant::now();
+        if print_summary {
+            eprintlnc!(
+                Green,
+                "Running commands {} from {}...",
+                quoted(&self.names),
+                self.file_path.display()
+            );
+        }
+    }
+
+    pub fn done(&mut self, cmd_name: &str, exit_code: i32) {
+        self.done.push((cmd_name.to_string(), exit_code));
+    }
+
+    // print the summary and return the exit code of the first command which failed
+    pub fn finish(self) -> i32 {
+        let failed: Vec<&(String, i32)> = self.done.iter().filter(|(_, c)| *c != 0).collect();
+        if self.print_summary == Some(true) {
+            let dur_str = format_duration(self.start.elapsed());
+            if failed.is_empty() {
+                eprintlnc!(Green, "Commands {} successful in {} 👍", quoted(&self.names), dur_str);
+            } else {
+                let mut details: Vec<String> = failed
+                    .iter()
+                    .map(|(name, c)| format!("\"{}\" exit code {}", name, c))
+                    .collect();
+                let skipped: Vec<String> = self.names[self.done.len()..].to_vec();
+                if !skipped.is_empty() {
+                    details.push(format!("skipped {}", quoted(&skipped)));
+                }
+                eprintlnc!(
+                    Yellow,
+                    "Commands {} failed in {}, {} 👎",
+                    quoted(&self.names),
+                    dur_str,
+                    details.join(", ")
+                );
+            }
+        }
+        failed.first().map_or(0, |(_, c)| *c)
+    }
+}
+
+fn quoted(names: &[String]) -> String {
+    let quoted: Vec<String> = names.iter().map(|n| format!("\"{}\"", n)).collect();
+    quoted.join(", ")
+}
+
 fn run_command_once(run: &Run, cmd: &Cmd) -> Result<i32, String> {
     if run.print_summary {
         eprintlnc!(
@@ -56,21 +134,81 @@ fn run_command_once(run: &Run, cmd: &Cmd) -> Result<i32, String> {
         );
     }
     let sig = register_signals().map_err(error_str)?;
-    let (exit_code, dur_str) = run_command(run, cmd)?;
-    if let Some(c) = exit_code {
-        Ok(c)
+    let start = Instant::now();
+    let log_file = open_log(&run.log, &run.cmd_name, &run.working_dir)?;
+    let mut delay = retry_delay(cmd.retry_delay);
+    let mut attempt: u32 = 1;
+    loop {
+        // with retries the summary is printed here so it can include the number of attempts
+        let (exit_code, dur_str) = run_command(run, cmd, log_file.clone(), run.print_summary && cmd.retries == 0)?;
+        match exit_code {
+            Some(c) if c != 0 && attempt <= cmd.retries && cmd.retry_on(c) => {
+                if run.print_summary {
+                    eprintlnc!(
+                        Yellow,
+                        "Attempt {} of command \"{}\" failed in {}, exit code {}, retrying in {}...",
+                        attempt,
+                        run.cmd_name,
+                        dur_str,
+                        c,
+                        format_duration(delay)
+                    );
+                }
+                if !wait(delay, &sig) {
+                    eprintlnc!(
+                        Yellow,
+                        "Retrying \"{}\" stopped with signal {} ✋",
+                        run.cmd_name,
+                        signal_name(&sig).unwrap_or("UNKNOWN")
+                    );
+                    return Ok(c);
+                }
+                delay = retry_delay(delay.as_secs_f32() * cmd.retry_backoff);
+                attempt += 1;
+            }
+            Some(c) => {
+                if run.print_summary && cmd.retries > 0 {
+                    print_summary_line(&run.cmd_name, &format_duration(start.elapsed()), c, attempt);
+                }
+                return Ok(c);
+            }
+            None => {
+                eprintlnc!(
+                    Yellow,
+                    "Command \"{}\" killed with signal {} after {} ✋",
+                    run.cmd_name,
+                    signal_name(&sig).unwrap_or("UNKNOWN"),
+                    dur_str
+                );
+                return Ok(99);
+            }
+        }
+    }
+}
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(3600);
+
+// delays are capped so large values of retry_backoff don't overflow
+fn retry_delay(secs: f32) -> Duration {
+    if secs.is_finite() && secs < MAX_RETRY_DELAY.as_secs_f32() {
+        Duration::from_secs_f32(secs.max(0.0))
     } else {
-        eprintlnc!(
-            Yellow,
-            "Command \"{}\" killed with signal {} after {} ✋",
-            run.cmd_name,
-            signal_name(&sig).unwrap_or("UNKNOWN"),
-            dur_str
-        );
-        Ok(99)
+        MAX_RETRY_DELAY
     }
 }
 
+// sleep before a retry, false if a signal was received meanwhile
+fn wait(delay: Duration, sig: &Signal) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < delay {
+        if signal_name(sig).is_some() {
+            return false;
+        }
+        sleep(Duration::from_millis(WAIT_MS).min(delay));
+    }
+    signal_name(sig).is_none()
+}
+
 const WAIT_MS: u64 = 20;
 
 fn run_command_watch(run: &Run, cmd: &Cmd) -> Result<i32, String> {
@@ -108,7 +246,8 @@ fn run_command_watch(run: &Run, cmd: &Cmd) -> Result<i32, String> {
             watch_stopped(&sig, &run.cmd_name, start.elapsed());
             return Ok(0);
         }
-        let running_process = start_command(run, cmd, &env)?;
+        let log_file = open_log(&run.log, &run.cmd_name, &run.working_dir)?;
+        let running_process = start_command(run, cmd, &env, log_file, run.print_summary)?;
         loop {
             if let Ok(evt) = rx.recv_timeout(recv_timeout) {
                 if include_path(&evt) {
@@ -137,8 +276,11 @@ fn run_command_watch(run: &Run, cmd: &Cmd) -> Result<i32, String> {
         eprintlnc!(Green, "Restarting \"{}\"...", run.cmd_name);
         if !running_process.finished.load(Ordering::Relaxed) {
             let pid = Pid::from_raw(running_process.process_id);
-            dbg!(pid);
-            kill(pid, NixSignal::SIGTERM).unwrap();
+            // the process may have finished since the check above
+            match kill(pid, NixSignal::SIGTERM) {
+                Ok(_) | Err(nix::Error::Sys(nix::errno::Errno::ESRCH)) => (),
+                Err(e) => return Err(error_str(e)),
+            }
         }
         running_process
             .handle
@@ -155,20 +297,22 @@ fn run_command_watch(run: &Run, cmd: &Cmd) -> Result<i32, String> {
 
 fn include_path(evt: &RawEvent) -> bool {
     // Ignore the following files which commonly don't reserve to be considered:
-    // .donk.tmp and .donk.tmp.<v> donkey-make files - important we ignore these to avoid constant reloading
+    // .donk.tmp, .donk.tmp.<v> and .donk.tmp.<v>.<ext> donkey-make files - important we ignore these to avoid constant reloading
+    // <command>.<timestamp>.log donkey-make log files - for the same reason
     // ___jb_tmp___ and ___jb_old___ temporary files from python
     // .pyc and friends python code bytes
     // .swp vim files
     // ~ linux temporary files
     lazy_static! {
         static ref IGNORE_PATH: Regex =
-            Regex::new(r"(?:\.donk\.tmp(?:\.\d+)?|___jb_.{3}___|\.py[cod]|\.sw.|~)$").unwrap();
+            Regex::new(r"(?:\.donk\.tmp(?:\.\d+)?(?:\.\w+)?|\.\d{4}-\d{2}-\d{2}T\d{2}-\d{2}-\d{2}\.\d{3}\.log|___jb_.{3}___|\.py[cod]|\.sw.|~)$")
+                .unwrap();
     }
     if let Ok(op::CLOSE_WRITE) = evt.op {
         return false;
     }
     if let Some(path) = &evt.path {
-        if IGNORE_PATH.is_match(&path.to_string_lossy().to_string()) {
+        if IGNORE_PATH.is_match(path.to_string_lossy().as_ref()) {
             return false;
         }
     }
@@ -185,8 +329,13 @@ fn watch_stopped(sig: &Signal, cmd_name: &str, duration: Duration) {
     );
 }
 
-fn run_command(run: &Run, cmd: &Cmd) -> Result<(Option<i32>, String), String> {
-    let rp = start_command(run, cmd, &run.env)?;
+fn run_command(
+    run: &Run,
+    cmd: &Cmd,
+    log_file: Option<LogFile>,
+    print_summary: bool,
+) -> Result<(Option<i32>, String), String> {
+    let rp = start_command(run, cmd, &run.env, log_file, print_summary)?;
     rp.handle.join().expect("Unable to join await_command thread")
 }
 
@@ -196,18 +345,39 @@ struct RunningProcess {
     pub handle: JoinHandle<Result<(Option<i32>, String), String>>,
 }
 
-fn start_command(run: &Run, cmd: &Cmd, envs: &Map<String, String>) -> Result<RunningProcess, String> {
-    let mut c = Command::new(cmd.executable());
-    c.args(&run.args).envs(envs).current_dir(&run.working_dir);
+fn start_command(
+    run: &Run,
+    cmd: &Cmd,
+    envs: &Map<String, String>,
+    log_file: Option<LogFile>,
+    print_summary: bool,
+) -> Result<RunningProcess, String> {
+    let capture = Capture::new(log_file, &run.prefix);
+    let mut c = match &cmd.container {
+        Some(container) => {
+            // a tty is only allocated when output isn't captured, as with commands run directly
+            let tty = capture.is_none() && atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout);
+            container::command(container, &run.executable, run, envs, tty)?
+        }
+        None => {
+            let mut c = Command::new(&run.executable);
+            c.args(&run.args).envs(envs);
+            c
+        }
+    };
+    c.current_dir(&run.working_dir);
+    if capture.is_some() {
+        Capture::pipe(&mut c);
+    }
 
     let cmd_name = run.cmd_name.clone();
-    let print_summary = run.print_summary;
     let start = Instant::now();
     let mut p = c.spawn().map_err(error_str)?;
+    let capture = capture.map(|cap| cap.attach(&mut p));
     let process_id = p.id() as i32;
     let finished = Arc::new(AtomicBool::new(false));
     let finished_clone = Arc::clone(&finished);
-    let handle = spawn(move || await_command(&mut p, cmd_name, print_summary, start, finished_clone));
+    let handle = spawn(move || await_command(&mut p, capture, cmd_name, print_summary, start, finished_clone));
     let rp = RunningProcess {
         process_id,
         finished,
@@ -218,6 +388,7 @@ fn start_command(run: &Run, cmd: &Cmd, envs: &Map<String, String>) -> Result<Run
 
 fn await_command(
     p: &mut Child,
+    capture: Option<Capture>,
     cmd_name: String,
     print_summary: bool,
     start: Instant,
@@ -226,25 +397,46 @@ fn await_command(
     let status = p.wait().map_err(error_str)?;
     let duration = start.elapsed();
     let dur_str = format_duration(duration);
+    if let Some(cap) = capture {
+        cap.finish(&match status.code() {
+            Some(c) => format!("exit code {} after {}", c, dur_str),
+            None => format!("killed by signal after {}", dur_str),
+        });
+    }
     finished.store(true, Ordering::Relaxed);
     if let Some(c) = status.code() {
         if print_summary {
-            if c == 0 {
-                eprintlnc!(Green, "Command \"{}\" successful in {} 👍", cmd_name, dur_str);
-            } else {
-                eprintlnc!(
-                    Yellow,
-                    "Command \"{}\" failed in {}, exit code {} 👎",
-                    cmd_name,
-                    dur_str,
-                    c
-                );
-            }
+            print_summary_line(&cmd_name, &dur_str, c, 1);
         }
     }
     Ok((status.code(), dur_str))
 }
 
+fn print_summary_line(cmd_name: &str, dur_str: &str, exit_code: i32, attempts: u32) {
+    let attempts_str = match attempts {
+        1 => String::new(),
+        a => format!(" after {} attempts", a),
+    };
+    if exit_code == 0 {
+        eprintlnc!(
+            Green,
+            "Command \"{}\" successful in {}{} 👍",
+            cmd_name,
+            dur_str,
+            attempts_str
+        );
+    } else {
+        eprintlnc!(
+            Yellow,
+            "Command \"{}\" failed in {}{}, exit code {} 👎",
+            cmd_name,
+            dur_str,
+            attempts_str,
+            exit_code
+        );
+    }
+}
+
 fn delete(path: &PathBuf, keep: bool) -> Result<(), String> {
     if !keep {
         match fs::remove_file(path) {
@@ -362,4 +554,21 @@ mod tests {
         let toc = tic + Duration::from_secs(200);
         assert_eq!(format_duration(toc.duration_since(tic).unwrap()), "200s");
     }
+
+    fn event(path: &str) -> RawEvent {
+        RawEvent {
+            path: Some(PathBuf::from(path)),
+            op: Ok(op::WRITE),
+            cookie: None,
+        }
+    }
+
+    #[test]
+    fn include_path_tmp_files() {
+        assert!(!include_path(&event("/src/.donk.tmp")));
+        assert!(!include_path(&event("/src/.donk.tmp.2")));
+        assert!(!include_path(&event("/src/.donk.tmp.2.ps1")));
+        assert!(!include_path(&event("/src/.donk.tmp.rb")));
+        assert!(include_path(&event("/src/main.rb")));
+    }
 }
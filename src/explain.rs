@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use ansi_term::Colour::{Cyan, Green, Red};
+
+use crate::commands::FileConfig;
+use crate::prepare::{DONK_PREFIX, INLINE_PREFIX};
+
+// print the tree of sub-commands called by each command, flagging references which would fail at runtime
+pub fn main(file_path: &Path, config: &FileConfig, cmd_name: &Option<String>) -> i32 {
+    let roots: Vec<String> = match cmd_name {
+        Some(c) => vec![c.clone()],
+        None => config.keys(),
+    };
+    let mut explainer = Explainer {
+        file_path,
+        config,
+        problems: 0,
+    };
+    for root in roots {
+        let mut tree: Vec<String> = Vec::new();
+        explainer.node(&root, None, "", "", &mut tree);
+    }
+    match explainer.problems {
+        0 => 0,
+        p => {
+            printlnc!(Red, "{} problem{} found", p, if p == 1 { "" } else { "s" });
+            1
+        }
+    }
+}
+
+struct Explainer<'a> {
+    file_path: &'a Path,
+    config: &'a FileConfig,
+    problems: usize,
+}
+
+impl<'a> Explainer<'a> {
+    fn node(&mut self, cmd_name: &str, reference: Option<char>, branch: &str, indent: &str, tree: &mut Vec<String>) {
+        let label = match reference {
+            Some(r) => format!("{}{}{}", branch, r, paint!(Cyan, cmd_name)),
+            None => paint!(Cyan, cmd_name),
+        };
+        let (cmd_name, cmd) = match self.config.get(cmd_name) {
+            Some(c) => c,
+            None => {
+                self.problem(&label, "command not found");
+                return;
+            }
+        };
+        let location = match self.config.line(cmd_name) {
+            Some(line) => format!("{}:{}", self.file_path.display(), line),
+            None => self.file_path.display().to_string(),
+        };
+        let mut details: Vec<String> = vec![format!("ex: {}", cmd.ex())];
+        if let Some(wd) = &cmd.working_dir {
+            details.push(format!("working dir: {}", wd));
+        }
+        if let Some(c) = &cmd.container {
+            details.push(format!("container: {}", c.image));
+        }
+        if let Some(w) = &cmd.watch {
+            details.push(format!("watch: {} (debounce {}s)", w, cmd.watch_debounce));
+        }
+        println!("{} {} {}", label, paint!(Green, location), details.join(", "));
+
+        let parent = tree.last().and_then(|p| self.config.get(p)).map(|(_, c)| c);
+        if let (Some(INLINE_PREFIX), Some(parent)) = (reference, parent) {
+            if !cmd.inlinable_in(parent) {
+                self.problem(
+                    indent,
+                    &format!(
+                        "not a {} script, remove \"ex:\" or use '{}' not '{}'",
+                        parent.ex(),
+                        DONK_PREFIX,
+                        INLINE_PREFIX
+                    ),
+                );
+            }
+        }
+        if tree.iter().any(|c| c == cmd_name) {
+            self.problem(indent, "recursive reference, this would never finish");
+            return;
+        }
+
+        tree.push(cmd_name.to_string());
+        let references = cmd.references();
+        let count = references.len();
+        for (i, (r, sub_cmd_name)) in references.iter().enumerate() {
+            let (sub_branch, sub_indent) = if i + 1 == count {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            self.node(
+                sub_cmd_name,
+                Some(*r),
+                &format!("{}{}", indent, sub_branch),
+                &format!("{}{}", indent, sub_indent),
+                tree,
+            );
+        }
+        tree.pop();
+    }
+
+    fn problem(&mut self, label: &str, msg: &str) {
+        self.problems += 1;
+        println!("{} {}", label, paint!(Red, format!("✗ {}", msg)));
+    }
+}
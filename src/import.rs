@@ -0,0 +1,667 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ansi_term::Colour::Yellow;
+use regex::{Captures, Regex};
+use serde_yaml::{Mapping, Value};
+
+use crate::commands;
+use crate::prepare::{DONK_PREFIX, NO_ECHO_PREFIX};
+
+// built-in command, only used if the config file doesn't define a command with the same name
+pub const COMMAND: &str = "import";
+
+const SOURCES: [&str; 7] = [
+    "Makefile",
+    "makefile",
+    "GNUmakefile",
+    "package.json",
+    "justfile",
+    "Justfile",
+    ".justfile",
+];
+
+// whether "donk import" should run a command from the config file rather than importing
+pub fn shadowed(file_path: &Option<String>) -> bool {
+    match commands::find_file(file_path) {
+        Ok(path) => match commands::load_file(&path) {
+            Ok(config) => config.commands.contains_key(COMMAND),
+            Err(_) => false,
+        },
+        Err(_) => false,
+    }
+}
+
+// convert a Makefile, package.json or justfile to donk's format, the result is printed to stdout
+// and anything which couldn't be translated is reported on stderr
+pub fn main(args: &[String]) -> Result<i32, String> {
+    let path = match args {
+        [] => find_source()?,
+        [p] => PathBuf::from(p),
+        _ => return err!("\"donk import\" takes at most one argument, the file to import"),
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) => return err!("Error reading {}:\n  {}", path.display(), e),
+    };
+    let file_name = match path.file_name() {
+        Some(n) => n.to_string_lossy().to_string(),
+        None => return err!("Invalid file to import {}", path.display()),
+    };
+    let imported = if file_name == "package.json" {
+        from_package_json(&content)?
+    } else if file_name.to_lowercase().ends_with("justfile") {
+        from_justfile(&content)
+    } else {
+        from_makefile(&content)
+    };
+    for w in &imported.warnings {
+        match w.line {
+            Some(line) => {
+                eprintlnc!(Yellow, "warning: {}:{}: {}", path.display(), line, w.msg);
+            }
+            None => {
+                eprintlnc!(Yellow, "warning: {}: {}", path.display(), w.msg);
+            }
+        }
+    }
+    print!("{}", imported.to_yaml(&path));
+    Ok(0)
+}
+
+fn find_source() -> Result<PathBuf, String> {
+    match SOURCES.iter().map(PathBuf::from).find(|p| p.is_file()) {
+        Some(p) => Ok(p),
+        None => err!(
+            "No file to import found, tried:\n  {}\nPass the file to import, eg. \"donk import path/to/Makefile\".",
+            SOURCES.join(", ")
+        ),
+    }
+}
+
+struct Warning {
+    line: Option<usize>,
+    msg: String,
+}
+
+struct ImportedCmd {
+    name: String,
+    description: Option<String>,
+    run: Vec<String>,
+}
+
+#[derive(Default)]
+struct Imported {
+    env: Vec<(String, String)>,
+    commands: Vec<ImportedCmd>,
+    warnings: Vec<Warning>,
+}
+
+impl Imported {
+    fn warn(&mut self, line: Option<usize>, msg: String) {
+        self.warnings.push(Warning { line, msg });
+    }
+
+    fn set_env(&mut self, name: &str, value: String) {
+        match self.env.iter_mut().find(|(k, _)| k == name) {
+            Some(v) => v.1 = value,
+            None => self.env.push((name.to_string(), value)),
+        }
+    }
+
+    fn to_yaml(&self, path: &Path) -> String {
+        let mut root = Mapping::new();
+        if !self.env.is_empty() {
+            let env: Mapping = self
+                .env
+                .iter()
+                .map(|(k, v)| (Value::String(k.clone()), Value::String(v.clone())))
+                .collect();
+            root.insert(Value::String(".env".to_string()), Value::Mapping(env));
+        }
+        for cmd in &self.commands {
+            let mut m = Mapping::new();
+            if let Some(d) = &cmd.description {
+                m.insert(Value::String("description".to_string()), Value::String(d.clone()));
+            }
+            let run = cmd.run.iter().map(|l| Value::String(l.clone())).collect();
+            m.insert(Value::String("run".to_string()), Value::Sequence(run));
+            root.insert(Value::String(cmd.name.clone()), Value::Mapping(m));
+        }
+        let yaml = serde_yaml::to_string(&root).expect("Unable to serialize imported commands");
+        format!(
+            "# imported from {} by donk import\n{}\n",
+            path.display(),
+            yaml.trim_start_matches("---\n")
+        )
+    }
+}
+
+fn from_package_json(content: &str) -> Result<Imported, String> {
+    let package: serde_json::Value = match serde_json::from_str(content) {
+        Ok(v) => v,
+        Err(e) => return err!("Error parsing package.json:\n  {}", e),
+    };
+    let scripts = match package.get("scripts").and_then(|s| s.as_object()) {
+        Some(s) => s,
+        None => return err!("No \"scripts\" found in package.json"),
+    };
+    lazy_static! {
+        static ref RUN_SCRIPT: Regex =
+            Regex::new(r"^(?:npm run|npm run-script|yarn run|yarn|pnpm run|pnpm) ([\w:.-]+)$").unwrap();
+    }
+    let names: HashSet<&str> = scripts.keys().map(String::as_str).collect();
+    // "npm run x" and equivalents calling another script become "+x"
+    let script_lines = |script: &str| -> Vec<String> {
+        script
+            .split(" && ")
+            .map(|part| match RUN_SCRIPT.captures(part.trim()) {
+                Some(c) if names.contains(&c[1]) => format!("{}{}", DONK_PREFIX, &c[1]),
+                _ => part.trim().to_string(),
+            })
+            .collect()
+    };
+    let mut imported = Imported::default();
+    for (name, script) in scripts {
+        let script = match script.as_str() {
+            Some(s) => s,
+            None => {
+                imported.warn(None, format!("script \"{}\" is not a string, skipped", name));
+                continue;
+            }
+        };
+        // pre and post scripts are run by npm around the main script
+        let pre = format!("pre{}", name);
+        let post = format!("post{}", name);
+        let mut run: Vec<String> = Vec::new();
+        if names.contains(pre.as_str()) {
+            run.push(format!("{}{}", DONK_PREFIX, pre));
+        }
+        run.extend(script_lines(script));
+        if names.contains(post.as_str()) {
+            run.push(format!("{}{}", DONK_PREFIX, post));
+        }
+        imported.commands.push(ImportedCmd {
+            name: name.clone(),
+            description: None,
+            run,
+        });
+    }
+    imported.warn(
+        None,
+        "npm adds node_modules/.bin to PATH when running scripts, donk does not".to_string(),
+    );
+    Ok(imported)
+}
+
+lazy_static! {
+    static ref MAKE_VAR: Regex =
+        Regex::new(r"^(?:export\s+|override\s+)?([A-Za-z_][\w.-]*)\s*(::=|:::=|:=|\?=|\+=|!=|=)\s*(.*)$").unwrap();
+    static ref MAKE_REF: Regex = Regex::new(r"\$(?:\$|\(([^)]*)\)|\{([^}]*)\}|(.))").unwrap();
+    static ref ENV_NAME: Regex = Regex::new(r"^[A-Za-z_]\w*$").unwrap();
+}
+
+const MAKE_DIRECTIVES: [&str; 13] = [
+    "include", "-include", "sinclude", "ifeq", "ifneq", "ifdef", "ifndef", "else", "endif", "define", "endef",
+    "unexport", "vpath",
+];
+
+struct Rule {
+    line: usize,
+    targets: Vec<String>,
+    deps: Vec<String>,
+    description: Option<String>,
+    recipe: Vec<(usize, String)>,
+}
+
+fn from_makefile(content: &str) -> Imported {
+    let mut imported = Imported::default();
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut in_define = false;
+
+    for (line_no, line) in join_continuations(content) {
+        if in_define {
+            in_define = line.trim() != "endef";
+            continue;
+        }
+        if let Some(recipe) = line.strip_prefix('\t') {
+            match rules.last_mut() {
+                Some(rule) => rule.recipe.push((line_no, recipe.to_string())),
+                None => imported.warn(Some(line_no), "recipe line outside a rule, ignored".to_string()),
+            }
+            continue;
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            comment = None;
+            continue;
+        }
+        if let Some(c) = trimmed.strip_prefix('#') {
+            comment = Some(c.trim_start_matches('#').trim().to_string());
+            continue;
+        }
+        let first_word = trimmed.split_whitespace().next().unwrap_or("");
+        if MAKE_DIRECTIVES.contains(&first_word) {
+            in_define = first_word == "define";
+            imported.warn(
+                Some(line_no),
+                format!("\"{}\" directive not supported, ignored", first_word),
+            );
+        } else if let Some(c) = MAKE_VAR.captures(trimmed) {
+            make_variable(&mut imported, line_no, &c[1], &c[2], &c[3]);
+        } else if let Some(rule) = make_rule(&mut imported, line_no, trimmed, comment.take()) {
+            rules.push(rule);
+        } else {
+            imported.warn(Some(line_no), "line not understood, ignored".to_string());
+        }
+        comment = None;
+    }
+
+    let targets: HashSet<String> = rules.iter().flat_map(|r| r.targets.clone()).collect();
+    for rule in rules {
+        for target in &rule.targets {
+            let mut run: Vec<String> = Vec::new();
+            for dep in &rule.deps {
+                if targets.contains(dep) {
+                    run.push(format!("{}{}", DONK_PREFIX, dep));
+                } else {
+                    imported.warn(
+                        Some(rule.line),
+                        format!("prerequisite \"{}\" of \"{}\" is not a target, ignored", dep, target),
+                    );
+                }
+            }
+            for (line_no, recipe) in &rule.recipe {
+                let line = make_recipe_line(&mut imported, *line_no, recipe, target, &rule.deps);
+                run.push(line);
+            }
+            if run.is_empty() {
+                imported.warn(
+                    Some(rule.line),
+                    format!("target \"{}\" has nothing to run, skipped", target),
+                );
+                continue;
+            }
+            imported.commands.push(ImportedCmd {
+                name: target.clone(),
+                description: rule.description.clone(),
+                run,
+            });
+        }
+    }
+    imported
+}
+
+// join lines ending with a backslash, keeping the number of the first line
+fn join_continuations(content: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (i, line) in content.lines().enumerate() {
+        let (line_no, mut joined) = match pending.take() {
+            Some((n, mut prev)) => {
+                prev.push(' ');
+                prev.push_str(line.trim_start());
+                (n, prev)
+            }
+            None => (i + 1, line.to_string()),
+        };
+        if joined.ends_with('\\') {
+            joined.pop();
+            pending = Some((line_no, joined.trim_end().to_string()));
+        } else {
+            lines.push((line_no, joined));
+        }
+    }
+    if let Some(p) = pending {
+        lines.push(p);
+    }
+    lines
+}
+
+fn make_variable(imported: &mut Imported, line_no: usize, name: &str, op: &str, value: &str) {
+    if !ENV_NAME.is_match(name) {
+        imported.warn(
+            Some(line_no),
+            format!(
+                "variable \"{}\" is not a valid environment variable name, ignored",
+                name
+            ),
+        );
+        return;
+    }
+    if op == "!=" || value.contains("$(shell ") {
+        imported.warn(
+            Some(line_no),
+            format!("shell assignment of \"{}\" not supported, ignored", name),
+        );
+        return;
+    }
+    if value.contains('$') {
+        imported.warn(
+            Some(line_no),
+            format!(
+                "value of \"{}\" references other variables, it won't be expanded by donk",
+                name
+            ),
+        );
+    }
+    let existing = imported.env.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+    let value = match (op, existing) {
+        ("?=", Some(_)) => return,
+        ("+=", Some(prev)) => format!("{} {}", prev, value),
+        _ => value.to_string(),
+    };
+    imported.set_env(name, value);
+}
+
+fn make_rule(imported: &mut Imported, line_no: usize, line: &str, comment: Option<String>) -> Option<Rule> {
+    let colon = line.find(':')?;
+    let (targets, rest) = (&line[..colon], line[colon + 1..].trim_start_matches(':'));
+    let (rest, inline_recipe) = match rest.find(';') {
+        Some(i) => (&rest[..i], Some(rest[i + 1..].trim().to_string())),
+        None => (rest, None),
+    };
+    // "target: deps ## description" is a common convention for self-documenting Makefiles
+    let (deps, description) = match rest.find('#') {
+        Some(i) => (&rest[..i], Some(rest[i..].trim_start_matches('#').trim().to_string())),
+        None => (rest, comment),
+    };
+    let mut names: Vec<String> = Vec::new();
+    for target in targets.split_whitespace() {
+        if target.starts_with('.') {
+            // special targets like .PHONY have no equivalent in donk
+            continue;
+        } else if target.contains('%') || target.contains('$') {
+            imported.warn(
+                Some(line_no),
+                format!("pattern or variable target \"{}\" not supported, ignored", target),
+            );
+        } else {
+            names.push(target.to_string());
+        }
+    }
+    let mut recipe = Vec::new();
+    if let Some(r) = inline_recipe {
+        recipe.push((line_no, r));
+    }
+    Some(Rule {
+        line: line_no,
+        targets: names,
+        deps: deps
+            .split_whitespace()
+            .filter(|d| *d != "|")
+            .map(ToString::to_string)
+            .collect(),
+        description: description.filter(|d| !d.is_empty()),
+        recipe,
+    })
+}
+
+fn make_recipe_line(imported: &mut Imported, line_no: usize, line: &str, target: &str, deps: &[String]) -> String {
+    let mut line = line.trim();
+    let mut no_echo = false;
+    let mut ignore_errors = false;
+    loop {
+        match line.chars().next() {
+            Some('@') => no_echo = true,
+            Some('-') => ignore_errors = true,
+            Some('+') => (),
+            _ => break,
+        }
+        line = line[1..].trim_start();
+    }
+    let converted = MAKE_REF.replace_all(line, |c: &Captures| {
+        let name = c.get(1).or_else(|| c.get(2)).or_else(|| c.get(3)).map(|m| m.as_str());
+        match name {
+            None => "$".to_string(),
+            Some("@") => target.to_string(),
+            Some("<") => deps.first().cloned().unwrap_or_default(),
+            Some("^") => deps.join(" "),
+            Some("MAKE") => {
+                imported.warn(
+                    Some(line_no),
+                    "$(MAKE) runs make, consider using \"+target\" instead".to_string(),
+                );
+                "make".to_string()
+            }
+            Some(n) if ENV_NAME.is_match(n) => format!("${{{}}}", n),
+            Some(n) => {
+                imported.warn(Some(line_no), format!("make expression \"{}\" not supported", n));
+                c[0].to_string()
+            }
+        }
+    });
+    let mut line = converted.to_string();
+    if ignore_errors {
+        line = format!("{} || true", line);
+    }
+    if no_echo {
+        line = format!("{}{}", NO_ECHO_PREFIX, line);
+    }
+    line
+}
+
+lazy_static! {
+    static ref JUST_VAR: Regex = Regex::new(r"^(?:export\s+)?([A-Za-z_][\w-]*)\s*:=\s*(.*)$").unwrap();
+    static ref JUST_RECIPE: Regex = Regex::new(r"^(@)?([A-Za-z_][\w-]*)([^:]*):((?:[^=].*)?)$").unwrap();
+    static ref JUST_INTERPOLATION: Regex = Regex::new(r"\{\{(.*?)\}\}").unwrap();
+    static ref JUST_STRING: Regex = Regex::new(r#"^(?:"([^"\\]*)"|'([^']*)')$"#).unwrap();
+}
+
+const JUST_IGNORED: [&str; 4] = ["set", "alias", "import", "mod"];
+
+struct Param {
+    name: String,
+    default: Option<String>,
+    variadic: bool,
+}
+
+fn from_justfile(content: &str) -> Imported {
+    let mut imported = Imported::default();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut comment: Option<String> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line_no = i + 1;
+        let line = lines[i];
+        i += 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            comment = None;
+            continue;
+        }
+        if let Some(c) = trimmed.strip_prefix('#') {
+            if !c.starts_with('!') {
+                comment = Some(c.trim().to_string());
+            }
+            continue;
+        }
+        if line.starts_with(|c: char| c.is_whitespace()) {
+            imported.warn(Some(line_no), "indented line outside a recipe, ignored".to_string());
+            continue;
+        }
+        let first_word = trimmed.split_whitespace().next().unwrap_or("");
+        if trimmed.starts_with('[') {
+            imported.warn(Some(line_no), format!("attribute {} not supported, ignored", trimmed));
+        } else if let Some(c) = JUST_VAR.captures(trimmed) {
+            match JUST_STRING.captures(c[2].trim()) {
+                Some(s) => {
+                    let value = s.get(1).or_else(|| s.get(2)).map_or("", |m| m.as_str());
+                    imported.set_env(&c[1], value.to_string());
+                }
+                None => imported.warn(
+                    Some(line_no),
+                    format!(
+                        "value of \"{}\" is an expression, only plain strings are supported, ignored",
+                        &c[1]
+                    ),
+                ),
+            }
+        } else if JUST_IGNORED.contains(&first_word) {
+            imported.warn(Some(line_no), format!("\"{}\" not supported, ignored", first_word));
+        } else if let Some(c) = JUST_RECIPE.captures(trimmed) {
+            let mut body: Vec<(usize, &str)> = Vec::new();
+            while i < lines.len() && (lines[i].trim().is_empty() || lines[i].starts_with(|c: char| c.is_whitespace())) {
+                if !lines[i].trim().is_empty() {
+                    body.push((i + 1, lines[i]));
+                }
+                i += 1;
+            }
+            let name = c[2].to_string();
+            let quiet = c.get(1).is_some();
+            if let Some(cmd) = just_recipe(&mut imported, line_no, name, quiet, &c[3], &c[4], &body, comment.take()) {
+                imported.commands.push(cmd);
+            }
+        } else {
+            imported.warn(Some(line_no), "line not understood, ignored".to_string());
+        }
+        comment = None;
+    }
+    imported
+}
+
+#[allow(clippy::too_many_arguments)]
+fn just_recipe(
+    imported: &mut Imported,
+    line_no: usize,
+    name: String,
+    quiet: bool,
+    params: &str,
+    deps: &str,
+    body: &[(usize, &str)],
+    description: Option<String>,
+) -> Option<ImportedCmd> {
+    if let Some((_, first)) = body.first() {
+        if first.trim().starts_with("#!") {
+            imported.warn(
+                Some(line_no),
+                format!("shebang recipe \"{}\" not supported, skipped", name),
+            );
+            return None;
+        }
+    }
+    let params: Vec<Param> = params
+        .split_whitespace()
+        .map(|p| {
+            let variadic = p.starts_with('+') || p.starts_with('*');
+            let p = p.trim_start_matches(['+', '*', '$']);
+            match p.find('=') {
+                Some(eq) => {
+                    let default = p[eq + 1..].trim_matches(['"', '\'']).to_string();
+                    Param {
+                        name: p[..eq].to_string(),
+                        default: Some(default),
+                        variadic,
+                    }
+                }
+                None => Param {
+                    name: p.to_string(),
+                    default: None,
+                    variadic,
+                },
+            }
+        })
+        .collect();
+
+    let mut run: Vec<String> = Vec::new();
+    let (before, after) = match deps.find("&&") {
+        Some(i) => (&deps[..i], &deps[i + 2..]),
+        None => (deps, ""),
+    };
+    if deps.contains('(') {
+        imported.warn(
+            Some(line_no),
+            format!("dependencies with arguments in \"{}\" not supported, ignored", name),
+        );
+    }
+    let dep_lines = |deps: &str| -> Vec<String> {
+        deps.split_whitespace()
+            .filter(|d| !d.contains(['(', ')', '"', '\'']))
+            .map(|d| format!("{}{}", DONK_PREFIX, d))
+            .collect()
+    };
+    run.extend(dep_lines(before));
+
+    let indent = body.first().map_or(0, |(_, l)| l.len() - l.trim_start().len());
+    for (body_line_no, line) in body {
+        let mut line = line.get(indent..).unwrap_or_else(|| line.trim_start());
+        let mut no_echo = quiet;
+        let mut ignore_errors = false;
+        loop {
+            match line.chars().next() {
+                Some('@') => no_echo = !quiet,
+                Some('-') => ignore_errors = true,
+                _ => break,
+            }
+            line = &line[1..];
+        }
+        let converted = JUST_INTERPOLATION.replace_all(line, |c: &Captures| {
+            let expr = c[1].trim();
+            if let Some(index) = params.iter().position(|p| p.name == expr) {
+                let p = &params[index];
+                match (&p.default, p.variadic) {
+                    (_, true) if index == 0 => "$@".to_string(),
+                    (_, true) => format!("${{@:{}}}", index + 1),
+                    (Some(d), false) => format!("${{{}:-{}}}", index + 1, d),
+                    (None, false) => format!("${}", index + 1),
+                }
+            } else if imported.env.iter().any(|(k, _)| k == expr) {
+                format!("${{{}}}", expr)
+            } else {
+                imported.warn(Some(*body_line_no), format!("expression \"{}\" not supported", &c[0]));
+                c[0].to_string()
+            }
+        });
+        let mut line = converted.to_string();
+        if ignore_errors {
+            line = format!("{} || true", line);
+        }
+        if no_echo {
+            line = format!("{}{}", NO_ECHO_PREFIX, line);
+        }
+        run.push(line);
+    }
+    run.extend(dep_lines(after));
+
+    if run.is_empty() {
+        imported.warn(
+            Some(line_no),
+            format!("recipe \"{}\" has nothing to run, skipped", name),
+        );
+        return None;
+    }
+    Some(ImportedCmd {
+        name,
+        description: description.filter(|d| !d.is_empty()),
+        run,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn makefile_recipe() {
+        let imported = from_makefile("CC = gcc\n\n# build it\nbuild: dep \\\n  other\n\t@$(CC) -o $@ $<\n");
+        assert_eq!(imported.env, vec![("CC".to_string(), "gcc".to_string())]);
+        assert_eq!(imported.commands.len(), 1);
+        let cmd = &imported.commands[0];
+        assert_eq!(cmd.name, "build");
+        assert_eq!(cmd.description, Some("build it".to_string()));
+        assert_eq!(cmd.run, vec!["_${CC} -o build dep"]);
+        assert_eq!(imported.warnings.len(), 2);
+    }
+
+    #[test]
+    fn justfile_params() {
+        let imported = from_justfile("# greet someone\ngreet name greeting='hi' +rest: setup\n  echo {{greeting}} {{name}} {{rest}}\nsetup:\n  @echo setup\n");
+        let cmd = &imported.commands[0];
+        assert_eq!(cmd.description, Some("greet someone".to_string()));
+        assert_eq!(cmd.run, vec!["+setup", "echo ${2:-hi} $1 ${@:3}"]);
+        assert_eq!(imported.commands[1].run, vec!["_echo setup"]);
+        assert!(imported.warnings.is_empty());
+    }
+}
@@ -0,0 +1,309 @@
+use std::path::Path;
+
+// how to write scripts for an executable, this lets smart scripts ("ex: <executable>-smart") work in any
+// of these languages
+pub struct Interpreter {
+    pub name: &'static str,
+    // executable names this applies to, versioned names like "python3.8" also match
+    executables: &'static [&'static str],
+    pub comment: &'static str,
+    // arguments to the executable before the path of the script, eg. "deno run"
+    pub args: &'static [&'static str],
+    // extension of the temporary file for interpreters which require one, eg. ".ps1"
+    pub extension: &'static str,
+    // start of smart scripts, makes them stop at the first failing line and defines "_donk" where required
+    pub prelude: &'static str,
+    // statement writing a line to stderr
+    pub echo: fn(&str) -> String,
+    // statement calling donk with the rest of a "+" line, stopping the script if it fails
+    pub call: fn(&str, &str) -> Result<String, String>,
+    // appended to one line smart scripts which don't use their arguments, so they're passed on
+    pub pass_args: Option<&'static str>,
+    // supports "trap ... ERR" and $LINENO, used to report which line of a smart script failed
+    pub trap_errors: bool,
+}
+
+pub const SMART_SUFFIX: &str = "-smart";
+
+// options "sh" has in POSIX, eg. "pipefail" isn't one of them
+const SH_OPTIONS: [&str; 13] = [
+    "allexport",
+    "errexit",
+    "ignoreeof",
+    "monitor",
+    "noclobber",
+    "noexec",
+    "noglob",
+    "nolog",
+    "notify",
+    "nounset",
+    "verbose",
+    "vi",
+    "xtrace",
+];
+
+impl Interpreter {
+    pub fn shell(&self) -> bool {
+        SHELLS.contains(&self.name)
+    }
+
+    // options are checked once the shell is known, bash and zsh support all the options used by "strict"
+    pub fn check_options(&self, options: &[String], strict: bool) -> Result<(), String> {
+        if self.name != "sh" {
+            return Ok(());
+        }
+        if strict {
+            return err!(
+                "strict can't be used with sh as it doesn't support \"pipefail\", use \"shell_options: [nounset]\""
+            );
+        }
+        match options.iter().find(|o| !SH_OPTIONS.contains(&o.as_str())) {
+            Some(o) => err!(
+                "shell option \"{}\" isn't supported by sh, options supported are: {}",
+                o,
+                SH_OPTIONS.join(", ")
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+pub const INTERPRETERS: [Interpreter; 8] = [
+    Interpreter {
+        name: "bash",
+        executables: &["bash"],
+        comment: "#",
+        args: &[],
+        extension: "",
+        prelude: "set -e",
+        echo: |line| format!(">&2 echo {}", sh_quote(line)),
+        call: |donk_exe, args| Ok(format!("{} {}", donk_exe, args)),
+        pass_args: Some("$@"),
+        trap_errors: true,
+    },
+    Interpreter {
+        name: "sh",
+        executables: &["sh", "dash", "ash"],
+        comment: "#",
+        args: &[],
+        extension: "",
+        prelude: "set -e",
+        // echo interprets backslashes in some shells, printf doesn't
+        echo: |line| format!(">&2 printf '%s\\n' {}", sh_quote(line)),
+        call: |donk_exe, args| Ok(format!("{} {}", donk_exe, args)),
+        pass_args: Some("\"$@\""),
+        trap_errors: false,
+    },
+    Interpreter {
+        name: "zsh",
+        executables: &["zsh"],
+        comment: "#",
+        args: &[],
+        extension: "",
+        prelude: "set -e",
+        echo: |line| format!(">&2 printf '%s\\n' {}", sh_quote(line)),
+        call: |donk_exe, args| Ok(format!("{} {}", donk_exe, args)),
+        pass_args: Some("$@"),
+        trap_errors: false,
+    },
+    Interpreter {
+        name: "python",
+        executables: &["python", "pypy"],
+        comment: "#",
+        args: &[],
+        extension: "",
+        // python stops at the first exception without help
+        prelude: "import subprocess, sys\n\
+                  def _donk(*args):\n    \
+                      r = subprocess.call(args)\n    \
+                      if r:\n        \
+                          sys.exit(r)",
+        echo: |line| format!("print({}, file=sys.stderr)", json_quote(line)),
+        call: |donk_exe, args| Ok(format!("_donk({})", quote_args(donk_exe, args, json_quote)?.join(", "))),
+        pass_args: None,
+        trap_errors: false,
+    },
+    Interpreter {
+        name: "node",
+        executables: &["node", "nodejs"],
+        comment: "//",
+        args: &[],
+        extension: "",
+        prelude: "function _donk(...args) {\n  \
+                  const r = require('child_process').spawnSync(args[0], args.slice(1), {stdio: 'inherit'})\n  \
+                  if (r.status !== 0) process.exit(r.status === null ? 1 : r.status)\n\
+                  }",
+        echo: |line| format!("console.error({})", json_quote(line)),
+        call: |donk_exe, args| Ok(format!("_donk({})", quote_args(donk_exe, args, json_quote)?.join(", "))),
+        pass_args: None,
+        trap_errors: false,
+    },
+    Interpreter {
+        name: "ruby",
+        executables: &["ruby"],
+        comment: "#",
+        args: &[],
+        extension: "",
+        prelude: "def _donk(*args)\n  \
+                  system(*args) or exit($?.exitstatus || 1)\n\
+                  end",
+        echo: |line| format!("$stderr.puts({})", ruby_quote(line)),
+        call: |donk_exe, args| Ok(format!("_donk({})", quote_args(donk_exe, args, ruby_quote)?.join(", "))),
+        pass_args: None,
+        trap_errors: false,
+    },
+    Interpreter {
+        name: "deno",
+        executables: &["deno"],
+        comment: "//",
+        args: &["run", "--allow-all"],
+        // deno decides the language from the extension
+        extension: ".ts",
+        prelude: "function _donk(...args: string[]) {\n  \
+                  const opts = {args: args.slice(1), stdout: 'inherit', stderr: 'inherit'} as const\n  \
+                  const r = new Deno.Command(args[0], opts).outputSync()\n  \
+                  if (!r.success) Deno.exit(r.code || 1)\n\
+                  }",
+        echo: |line| format!("console.error({})", json_quote(line)),
+        call: |donk_exe, args| Ok(format!("_donk({})", quote_args(donk_exe, args, json_quote)?.join(", "))),
+        pass_args: None,
+        trap_errors: false,
+    },
+    Interpreter {
+        name: "pwsh",
+        executables: &["pwsh", "powershell"],
+        comment: "#",
+        args: &["-NoProfile", "-NonInteractive", "-File"],
+        // "-File" refuses scripts without this extension
+        extension: ".ps1",
+        prelude: "$ErrorActionPreference = 'Stop'\n\
+                  $PSNativeCommandUseErrorActionPreference = $true\n\
+                  function _donk { & $args[0] $args[1..$args.Length]; if ($LASTEXITCODE) { exit $LASTEXITCODE } }",
+        echo: |line| format!("[Console]::Error.WriteLine({})", pwsh_quote(line)),
+        call: |donk_exe, args| Ok(format!("_donk {}", quote_args(donk_exe, args, pwsh_quote)?.join(" "))),
+        pass_args: None,
+        trap_errors: false,
+    },
+];
+
+// find the interpreter for an executable, eg. "python3.8" or "/usr/bin/node"
+pub fn find(executable: &str) -> Option<&'static Interpreter> {
+    let file_name = Path::new(executable)
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = file_name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    INTERPRETERS.iter().find(|i| i.executables.contains(&name))
+}
+
+// find the interpreter of a "#!" line, eg. "/usr/bin/python3" or "/usr/bin/env -S deno run"
+pub fn from_shebang(shebang: &str) -> Option<&'static Interpreter> {
+    let mut words = shebang.split_whitespace();
+    let program = words.next()?;
+    if Path::new(program).file_name() == Some("env".as_ref()) {
+        // skip options and variables set by env
+        words.find(|w| !w.starts_with('-') && !w.contains('=')).and_then(find)
+    } else {
+        find(program)
+    }
+}
+
+// interpreters which can be used as the shell for commands without "ex:"
+const SHELLS: [&str; 3] = ["bash", "sh", "zsh"];
+
+pub fn check_shell(shell: &str) -> Result<(), String> {
+    match find(shell) {
+        Some(i) if i.shell() => Ok(()),
+        _ => err!(
+            "shell \"{}\" not supported, it should be {} or the path to one of them",
+            shell,
+            SHELLS.join(", ")
+        ),
+    }
+}
+
+// names for "set -o", they're checked here as they're written into the script
+pub fn check_shell_options(options: &[String]) -> Result<(), String> {
+    match options
+        .iter()
+        .find(|o| o.is_empty() || !o.chars().all(|c| c.is_ascii_lowercase()))
+    {
+        Some(o) => err!(
+            "invalid shell option \"{}\", options should be names like \"pipefail\"",
+            o
+        ),
+        None => Ok(()),
+    }
+}
+
+pub fn names() -> Vec<&'static str> {
+    INTERPRETERS.iter().map(|i| i.name).collect()
+}
+
+// arguments of a "+" call as separate strings, in languages other than shells they're split following
+// shell quoting rules so "+foo 'a b'" works as it does in a shell
+fn quote_args(donk_exe: &str, args: &str, quote: fn(&str) -> String) -> Result<Vec<String>, String> {
+    let words = shell_words::split(args).map_err(|e| format!("invalid quoting in \"+{}\": {}", args, e))?;
+    let mut quoted = vec![quote(donk_exe)];
+    quoted.extend(words.iter().map(|w| quote(w)));
+    Ok(quoted)
+}
+
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+// JSON strings are valid python, javascript and typescript strings
+fn json_quote(s: &str) -> String {
+    serde_json::to_string(s).unwrap()
+}
+
+fn ruby_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn pwsh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_versions() {
+        assert_eq!(find("bash").map(|i| i.name), Some("bash"));
+        assert_eq!(find("python3.8").map(|i| i.name), Some("python"));
+        assert_eq!(find("/usr/bin/nodejs").map(|i| i.name), Some("node"));
+        assert_eq!(find("perl").map(|i| i.name), None);
+    }
+
+    #[test]
+    fn shebangs() {
+        assert_eq!(from_shebang("/usr/bin/python3 -u").map(|i| i.name), Some("python"));
+        assert_eq!(from_shebang("/usr/bin/env node").map(|i| i.name), Some("node"));
+        assert_eq!(
+            from_shebang("/usr/bin/env -S X=1 deno run").map(|i| i.name),
+            Some("deno")
+        );
+        assert_eq!(from_shebang("/usr/bin/env perl -w").map(|i| i.name), None);
+    }
+
+    #[test]
+    fn quoting() {
+        let python = find("python").unwrap();
+        assert_eq!((python.echo)("it's \"x\""), r#"print("it's \"x\"", file=sys.stderr)"#);
+        assert_eq!(
+            (python.call)("/bin/donk", "test  -v"),
+            Ok(r#"_donk("/bin/donk", "test", "-v")"#.to_string())
+        );
+        assert_eq!(
+            (python.call)("/bin/donk", r#"test 'a b' "it's" c\ d"#),
+            Ok(r#"_donk("/bin/donk", "test", "a b", "it's", "c d")"#.to_string())
+        );
+        assert!((python.call)("/bin/donk", "test 'a").is_err());
+        let bash = find("bash").unwrap();
+        assert_eq!((bash.echo)("it's"), r#">&2 echo 'it'\''s'"#);
+        assert_eq!((bash.call)("/bin/donk", "test -v"), Ok("/bin/donk test -v".to_string()));
+    }
+}
@@ -0,0 +1,67 @@
+use linked_hash_map::LinkedHashMap as Map;
+
+use crate::commands::FileConfig;
+
+#[derive(Serialize)]
+struct Listed<'a> {
+    name: &'a str,
+    aliases: &'a Vec<String>,
+    description: String,
+    executable: String,
+    lines: usize,
+    env: &'a Map<String, String>,
+    working_dir: &'a Option<String>,
+    watch: &'a Option<String>,
+    group: &'a Option<String>,
+    hidden: bool,
+}
+
+// machine readable list of commands for --list, used by editor plugins and other tools
+pub fn main(config: &FileConfig, format: &str, all: bool) -> String {
+    let listed: Vec<Listed> = config
+        .commands
+        .iter()
+        .filter(|(name, cmd)| all || !cmd.hidden(name))
+        .map(|(name, cmd)| Listed {
+            name,
+            aliases: &cmd.aliases,
+            description: cmd.description(),
+            executable: cmd.executable(),
+            lines: cmd.run.iter().map(|r| r.lines().count()).sum(),
+            env: &cmd.env,
+            working_dir: &cmd.working_dir,
+            watch: &cmd.watch,
+            group: &cmd.group,
+            hidden: cmd.hidden(name),
+        })
+        .collect();
+    match format {
+        "json" => format!(
+            "{}\n",
+            serde_json::to_string_pretty(&listed).expect("Unable to serialize commands")
+        ),
+        "yaml" => {
+            let yaml = serde_yaml::to_string(&listed).expect("Unable to serialize commands");
+            format!("{}\n", yaml.trim_start_matches("---\n"))
+        }
+        _ => listed.iter().map(plain_line).collect(),
+    }
+}
+
+// tab separated: name, description, executable, lines, env as "KEY=value" separated by spaces, working dir
+// and watch path, missing values are empty
+fn plain_line(l: &Listed) -> String {
+    let env: Vec<String> = l.env.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    let cells = [
+        l.name.to_string(),
+        l.description.clone(),
+        l.executable.clone(),
+        l.lines.to_string(),
+        env.join(" "),
+        l.working_dir.clone().unwrap_or_default(),
+        l.watch.clone().unwrap_or_default(),
+    ];
+    // tabs and newlines would break the columns and rows
+    let cells: Vec<String> = cells.iter().map(|c| c.replace(['\t', '\n'], " ")).collect();
+    format!("{}\n", cells.join("\t"))
+}
@@ -20,12 +20,20 @@ use std::string::ToString;
 use ansi_term::Colour::{Cyan, Green, Red};
 
 use crate::commands::{Cmd, FileConfig};
-use crate::utils::{CliArgs, DONKEY_KEEP_ENV};
+use crate::utils::{suggestion, CliArgs, Verbosity, DONKEY_KEEP_ENV, DONKEY_SHELL_ENV};
 
+mod check;
 mod commands;
 mod completion;
+mod container;
 mod execute;
+mod explain;
+mod import;
+mod interpreter;
+mod list;
+mod output;
 mod prepare;
+mod schema;
 mod utils;
 
 fn main() {
@@ -48,24 +56,131 @@ fn run() -> Result<i32, String> {
     }
 
     let cli = parse_args();
+    if cli.schema {
+        println!("{}", schema::main());
+        return Ok(0);
+    }
+    if cli.command.as_deref() == Some(import::COMMAND) && !import::shadowed(&cli.file_path) {
+        return import::main(&cli.args);
+    }
     let file_path = commands::find_file(&cli.file_path)?;
+    if cli.check {
+        return check::main(&file_path);
+    }
+
+    let mut config = commands::load_file(&file_path)?;
+    if let Some(shell) = &cli.shell {
+        config.set_shell(shell)?;
+        config.check_shell_options()?;
+    }
 
-    let config = commands::load_file(&file_path)?;
+    if cli.list {
+        print!("{}", list::main(&config, &cli.format, cli.all));
+        return Ok(0);
+    }
+
+    if cli.explain {
+        let root = match &cli.command {
+            Some(c) => Some(get_command(&config, c)?.0.clone()),
+            None => None,
+        };
+        return Ok(explain::main(&file_path, &config, &root));
+    }
 
-    let command_name = match &cli.command {
+    let command = match &cli.command {
         Some(c) => c,
         _ => {
-            help_message(&file_path, &config);
+            help_message(&file_path, &config, cli.all);
             return Ok(0);
         }
     };
-    let cmd = get_command(&config, &command_name)?;
+    let mut commands = split_commands(&config, command, &cli)?;
+    if commands.len() > 1 {
+        return run_sequence(&config, &commands, &cli, &file_path);
+    }
 
-    let run = prepare::main(&command_name, &config, &cmd, &cli, &file_path)?;
-    let c = execute::main(&run, &cmd, &cli)?;
+    let (command, args) = commands.remove(0);
+    let (command_name, cmd) = get_command(&config, &command)?;
+    let run = prepare::main(command_name, &config, cmd, &cli, &args, &file_path)?;
+    if cli.dry_run {
+        return Ok(0);
+    }
+    let c = execute::main(&run, cmd, &cli)?;
     Ok(c)
 }
 
+const COMMAND_SEP: &str = "+";
+
+// split the command line into the commands to run and their arguments, commands are separated by "+"
+// or are all the arguments if every argument is a command
+fn split_commands(config: &FileConfig, command: &str, cli: &CliArgs) -> Result<Vec<(String, Vec<String>)>, String> {
+    let trailing_start = cli.args.len() - cli.trailing_args.min(cli.args.len());
+    let (words, trailing) = cli.args.split_at(trailing_start);
+    let mut commands: Vec<(String, Vec<String>)> = vec![(command.to_string(), Vec::new())];
+    if words.iter().any(|w| w == COMMAND_SEP) {
+        let mut next_command = false;
+        for word in words {
+            if word == COMMAND_SEP {
+                if next_command {
+                    return err!("Expected a command after \"{}\"", COMMAND_SEP);
+                }
+                next_command = true;
+            } else if next_command {
+                commands.push((word.clone(), Vec::new()));
+                next_command = false;
+            } else if let Some((_, args)) = commands.last_mut() {
+                args.push(word.clone());
+            }
+        }
+        if next_command {
+            return err!("Expected a command after \"{}\"", COMMAND_SEP);
+        }
+    } else if !words.is_empty() && words.iter().all(|w| config.get(w).is_some()) {
+        commands.extend(words.iter().map(|w| (w.clone(), Vec::new())));
+    } else {
+        commands[0].1.extend(words.iter().cloned());
+    }
+    if let Some((_, args)) = commands.last_mut() {
+        args.extend(trailing.iter().cloned());
+    }
+    Ok(commands)
+}
+
+// run commands one after the other with a single summary, stopping at the first failure unless --keep-going
+fn run_sequence(
+    config: &FileConfig,
+    commands: &[(String, Vec<String>)],
+    cli: &CliArgs,
+    file_path: &Path,
+) -> Result<i32, String> {
+    let mut resolved: Vec<(&String, &Cmd, &Vec<String>)> = Vec::with_capacity(commands.len());
+    for (command, args) in commands {
+        let (name, cmd) = get_command(config, command)?;
+        if cmd.watch.is_some() || cli.watch_path.is_some() {
+            return err!("Command \"{}\" can't be watched when running multiple commands", name);
+        }
+        resolved.push((name, cmd, args));
+    }
+    let names: Vec<&String> = resolved.iter().map(|(name, _, _)| *name).collect();
+    let mut sequence = execute::Sequence::new(&names, file_path);
+    for (name, cmd, args) in resolved {
+        let mut run = prepare::main(name, config, cmd, cli, args, file_path)?;
+        if cli.dry_run {
+            continue;
+        }
+        if sequence.print_summary.is_none() {
+            sequence.start(run.print_summary);
+        }
+        run.print_summary = false;
+        let c = execute::main(&run, cmd, cli)?;
+        sequence.done(name, c);
+        if c != 0 && !cli.keep_going {
+            break;
+        }
+    }
+    Ok(sequence.finish())
+}
+
 fn parse_args() -> CliArgs {
     let cli_yaml = load_yaml!("cli.yaml");
     let mut version = get_version();
@@ -84,6 +199,11 @@ fn parse_args() -> CliArgs {
         Some(a) => a.map(ToString::to_string).collect(),
         None => Vec::new(),
     };
+    let trailing_args = match env::args().position(|a| a == "--") {
+        Some(i) => env::args().count() - i - 1,
+        None => 0,
+    };
+    let keep_going = raw_args.is_present("keep_going");
 
     if let Some(cc_) = raw_args.value_of("command") {
         if cc_.starts_with("./") {
@@ -111,77 +231,136 @@ fn parse_args() -> CliArgs {
         }
     };
 
-    let watch_path = match raw_args.value_of("watch_path") {
-        Some(w) => Some(w.to_string()),
-        None => None,
+    let watch_path = raw_args.value_of("watch_path").map(|w| w.to_string());
+    let log_dir = raw_args.value_of("log_dir").map(|l| l.to_string());
+    let prefix_output = raw_args.is_present("prefix_output");
+    let shell = match raw_args.value_of("shell") {
+        Some(s) => Some(s.to_string()),
+        None => env::var(DONKEY_SHELL_ENV).ok(),
+    };
+    let dry_run = raw_args.is_present("dry_run");
+    let explain = raw_args.is_present("explain");
+    let check = raw_args.is_present("check");
+    let schema = raw_args.is_present("schema");
+    let list = raw_args.is_present("list");
+    let all = raw_args.is_present("all");
+    let format = raw_args.value_of("format").unwrap_or("plain").to_string();
+
+    let verbosity = if raw_args.is_present("quiet") {
+        Verbosity::Quiet
+    } else {
+        match raw_args.occurrences_of("verbose") {
+            0 => Verbosity::from_env(),
+            1 => Verbosity::Verbose,
+            _ => Verbosity::Debug,
+        }
     };
 
     CliArgs {
         file_path,
         command,
         args,
+        trailing_args,
+        keep_going,
         keep_tmp,
         watch_path,
+        log_dir,
+        prefix_output,
+        verbosity,
+        shell,
+        dry_run,
+        explain,
+        check,
+        schema,
+        list,
+        format,
+        all,
     }
 }
 
-fn get_command<'a>(config: &'a FileConfig, command_name: &str) -> Result<&'a Cmd, String> {
-    Ok(match config.commands.get(command_name) {
-        Some(c) => c,
-        None => {
-            let keys = config.keys();
-            return err!(
-                "Command \"{}\" not found, commands available are:\n  {}{}",
-                command_name,
-                keys.join(", "),
-                suggestion(command_name, &keys)
-            );
-        }
-    })
-}
-
-fn suggestion(v: &str, possibilities: &[String]) -> String {
-    let mut threshold: f64 = 0.8;
-    let mut candidate: Option<&String> = None;
-    for pv in possibilities {
-        let confidence = strsim::jaro_winkler(v, &pv);
-        if confidence > threshold {
-            threshold = confidence;
-            candidate = Some(pv);
-        }
+// find the command by name or alias, the real name of the command is returned with it
+fn get_command<'a>(config: &'a FileConfig, command_name: &str) -> Result<(&'a String, &'a Cmd), String> {
+    if let Some(c) = config.get(command_name) {
+        return Ok(c);
     }
-    match candidate {
-        Some(c) => paint!(Cyan, format!("\n\n    perhaps you meant \"{}\"?", c)),
-        _ => "".to_string(),
+    if config.settings.prefix_matching {
+        match config.prefix_matches(command_name).as_slice() {
+            [] => (),
+            [name] => return Ok((name, &config.commands[*name])),
+            names => {
+                let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+                return err!(
+                    "Command \"{}\" is ambiguous, it could be any of:\n  {}",
+                    command_name,
+                    names.join(", ")
+                );
+            }
+        }
     }
+    err!(
+        "Command \"{}\" not found, commands available are:\n  {}{}",
+        command_name,
+        config.keys().join(", "),
+        suggestion(command_name, &config.names(true))
+    )
 }
 
 const PAD_TO: usize = 14;
 
 fn summary(key: &str, config: &FileConfig) -> String {
     let cmd = &config.commands[key];
-    let pad = match key.chars().count() {
+    let mut names = vec![key.to_string()];
+    names.extend(cmd.aliases.iter().cloned());
+    let names = names.join(", ");
+    let pad = match names.chars().count() {
         l if l < PAD_TO => PAD_TO - l,
         _ => 0,
     };
     format!(
         "{}{} {} {}",
-        paint!(Cyan, key),
+        paint!(Cyan, names),
         " ".repeat(pad),
         paint!(Green, cmd.summary()),
         cmd.description()
     )
 }
 
-fn help_message(file_path: &Path, config: &FileConfig) {
-    let commands: Vec<String> = config.keys().iter().map(|k| summary(k, &config)).collect();
+fn help_message(file_path: &Path, config: &FileConfig, all: bool) {
+    let keys = config.visible_keys(all);
+    // commands without a group come first, then each group in the order it first appears
+    let mut groups: Vec<(Option<&String>, Vec<String>)> = Vec::new();
+    for key in &keys {
+        let group = config.commands[key].group.as_ref();
+        match groups.iter_mut().find(|(g, _)| *g == group) {
+            Some((_, lines)) => lines.push(summary(key, config)),
+            None => groups.push((group, vec![summary(key, config)])),
+        }
+    }
+    groups.sort_by_key(|(g, _)| g.is_some());
+    let sections: Vec<String> = groups
+        .iter()
+        .map(|(group, lines)| match group {
+            Some(g) => format!("\n{}:\n  {}", g, lines.join("\n  ")),
+            None => format!("  {}", lines.join("\n  ")),
+        })
+        .collect();
     printlnc!(
         Green,
-        "donkey-make {}, commands available from {}:\n  {}",
+        "donkey-make {}, commands available from {}:\n{}",
         get_version(),
         file_path.display(),
-        commands.join("\n  ")
+        sections.join("\n")
     );
+    let hidden = config.commands.len() - keys.len();
+    if hidden > 0 {
+        printlnc!(
+            Green,
+            "\n{} hidden command{}, use \"--all\" to show {}",
+            hidden,
+            if hidden == 1 { "" } else { "s" },
+            if hidden == 1 { "it" } else { "them" }
+        );
+    }
 }
 
 fn get_version() -> String {
@@ -0,0 +1,308 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{spawn, JoinHandle};
+
+use ansi_term::Colour::{Fixed, Red};
+use regex::Regex;
+
+use crate::utils::{full_path, timestamp};
+
+const BUFFER_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub struct Log {
+    pub dir: PathBuf,
+    pub keep: Option<u32>,
+}
+
+impl Log {
+    // create a new timestamped log file for this command, then remove old log files beyond "keep"
+    fn create(&self, cmd_name: &str) -> Result<(PathBuf, File), String> {
+        if let Err(e) = fs::create_dir_all(&self.dir) {
+            return err!("Error creating log directory {}:\n  {}", self.dir.display(), e);
+        }
+        let prefix = file_prefix(cmd_name);
+        let path = self.dir.join(format!("{}{}.log", prefix, timestamp()));
+        let file = match File::create(&path) {
+            Ok(f) => f,
+            Err(e) => return err!("Error creating log file {}:\n  {}", path.display(), e),
+        };
+        if let Some(keep) = self.keep {
+            self.rotate(&prefix, keep as usize)?;
+        }
+        Ok((path, file))
+    }
+
+    fn rotate(&self, prefix: &str, keep: usize) -> Result<(), String> {
+        lazy_static! {
+            static ref LOG_SUFFIX: Regex = Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}-\d{2}-\d{2}\.\d{3}\.log$").unwrap();
+        }
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(e) => e,
+            Err(e) => return err!("Error reading log directory {}:\n  {}", self.dir.display(), e),
+        };
+        let mut logs: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| match p.file_name() {
+                Some(n) => {
+                    let name = n.to_string_lossy();
+                    name.starts_with(prefix) && LOG_SUFFIX.is_match(&name[prefix.len()..])
+                }
+                None => false,
+            })
+            .collect();
+        // timestamps sort lexically, so the oldest logs come first
+        logs.sort();
+        if logs.len() > keep {
+            for path in &logs[..logs.len() - keep] {
+                if let Err(e) = fs::remove_file(path) {
+                    return err!("Error deleting old log file {}:\n  {}", path.display(), e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn file_prefix(cmd_name: &str) -> String {
+    let safe_name: String = cmd_name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    format!("{}.", safe_name)
+}
+
+pub type LogFile = Arc<Mutex<File>>;
+
+// create the log file for a run of the command, shared by all its attempts when it's retried
+pub fn open_log(log: &Option<Log>, cmd_name: &str, working_dir: &Path) -> Result<Option<LogFile>, String> {
+    let l = match log {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+    let (path, mut file) = l.create(cmd_name)?;
+    let header = format!(
+        "# donkey-make log for command \"{}\" started at {} in {}\n",
+        cmd_name,
+        timestamp(),
+        full_path(working_dir)
+    );
+    if let Err(e) = file.write_all(header.as_bytes()) {
+        return err!("Error writing log file {}:\n  {}", path.display(), e);
+    }
+    Ok(Some(Arc::new(Mutex::new(file))))
+}
+
+// Relays the stdout and stderr of a child process to the terminal, optionally prefixing each line
+// with the command path and/or copying the output to a log file
+pub struct Capture {
+    file: Option<LogFile>,
+    prefix: Option<String>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl Capture {
+    pub fn new(file: Option<LogFile>, prefix: &Option<String>) -> Option<Capture> {
+        if file.is_none() && prefix.is_none() {
+            return None;
+        }
+        Some(Capture {
+            file,
+            prefix: prefix.clone(),
+            readers: Vec::with_capacity(2),
+        })
+    }
+
+    pub fn pipe(c: &mut Command) {
+        c.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    pub fn attach(mut self, p: &mut Child) -> Capture {
+        if let Some(stdout) = p.stdout.take() {
+            let file = self.file.clone();
+            let reader = match &self.prefix {
+                Some(path) => {
+                    let path = path.clone();
+                    let tty = atty::is(atty::Stream::Stdout);
+                    spawn(move || prefix_lines(stdout, io::stdout(), tty, &path, STDOUT_SEP, file))
+                }
+                None => spawn(move || tee(stdout, io::stdout(), file)),
+            };
+            self.readers.push(reader);
+        }
+        if let Some(stderr) = p.stderr.take() {
+            let file = self.file.clone();
+            let reader = match &self.prefix {
+                Some(path) => {
+                    let path = path.clone();
+                    let tty = atty::is(atty::Stream::Stderr);
+                    spawn(move || prefix_lines(stderr, io::stderr(), tty, &path, STDERR_SEP, file))
+                }
+                None => spawn(move || tee(stderr, io::stderr(), file)),
+            };
+            self.readers.push(reader);
+        }
+        self
+    }
+
+    // wait for all output to be written, then record how the command finished
+    pub fn finish(self, footer: &str) {
+        for reader in self.readers {
+            reader.join().expect("Unable to join output reader thread");
+        }
+        if let Some(file) = self.file {
+            if let Ok(mut f) = file.lock() {
+                // errors writing the log are ignored, they shouldn't change the result of the command
+                let _ = writeln!(f, "# {}", footer);
+            }
+        }
+    }
+}
+
+fn tee<R: Read, W: Write>(mut src: R, mut terminal: W, file: Option<Arc<Mutex<File>>>) {
+    let mut buf = [0; BUFFER_SIZE];
+    loop {
+        let n = match src.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let _ = terminal.write_all(&buf[..n]);
+        let _ = terminal.flush();
+        write_log(&file, &buf[..n]);
+    }
+}
+
+const STDOUT_SEP: &str = "|";
+const STDERR_SEP: &str = "!";
+const PATH_SEP: &str = " › ";
+const COLOURS: [u8; 10] = [6, 3, 2, 5, 4, 14, 11, 10, 13, 12];
+
+fn prefix_lines<R: Read, W: Write>(
+    src: R,
+    mut terminal: W,
+    tty: bool,
+    path: &str,
+    sep: &str,
+    file: Option<Arc<Mutex<File>>>,
+) {
+    let nested_start = format!("{}{}", path, PATH_SEP);
+    let mut reader = BufReader::new(src);
+    let mut line: Vec<u8> = Vec::with_capacity(BUFFER_SIZE);
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => (),
+        };
+        write_log(&file, &line);
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\n', '\r']);
+        let prefixed = if text.starts_with(&nested_start) {
+            // output of a nested donkey-make command which has already been prefixed
+            nested_line(text, path, tty)
+        } else {
+            format!("{} {}", paint_path(path, sep, tty), text)
+        };
+        let _ = writeln!(terminal, "{}", prefixed);
+        let _ = terminal.flush();
+    }
+}
+
+fn nested_line(text: &str, path: &str, tty: bool) -> String {
+    if tty {
+        if let Some(i) = nested_path_end(text, path) {
+            let sep = &text[i + 1..i + 2];
+            return format!("{}{}", paint_path(&text[..i], sep, tty), &text[i + 2..]);
+        }
+    }
+    text.to_string()
+}
+
+// end of the path of a nested command, eg. "all › test › lint | ...", the names after "path" are
+// single words so separators in the output itself are ignored
+fn nested_path_end(text: &str, path: &str) -> Option<usize> {
+    let mut i = path.len();
+    while text[i..].starts_with(PATH_SEP) {
+        i += PATH_SEP.len();
+        i += text[i..].find(' ').filter(|&n| n > 0)?;
+    }
+    let sep = text.get(i..i + 3)?;
+    if i > path.len() && (sep == format!(" {} ", STDOUT_SEP) || sep == format!(" {} ", STDERR_SEP)) {
+        Some(i)
+    } else {
+        None
+    }
+}
+
+fn paint_path(path: &str, sep: &str, tty: bool) -> String {
+    if tty {
+        let sep_colour = if sep == STDERR_SEP {
+            Red
+        } else {
+            Fixed(colour_index(path))
+        };
+        format!("{} {}", Fixed(colour_index(path)).paint(path), sep_colour.paint(sep))
+    } else {
+        format!("{} {}", path, sep)
+    }
+}
+
+// choose a colour for each command path, the same path always gets the same colour
+fn colour_index(path: &str) -> u8 {
+    let sum: usize = path.bytes().map(|b| b as usize).sum();
+    COLOURS[sum % COLOURS.len()]
+}
+
+fn write_log(file: &Option<Arc<Mutex<File>>>, data: &[u8]) {
+    if let Some(f) = file {
+        if let Ok(mut f) = f.lock() {
+            let _ = f.write_all(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_prefix_safe() {
+        assert_eq!(file_prefix("foo"), "foo.");
+        assert_eq!(file_prefix("foo/bar baz"), "foo_bar_baz.");
+    }
+
+    #[test]
+    fn nested_line_plain() {
+        assert_eq!(nested_line("all › test | hello", "all", false), "all › test | hello");
+    }
+
+    #[test]
+    fn nested_line_painted() {
+        let expected = format!(
+            "{} {} hello",
+            Fixed(colour_index("all › test")).paint("all › test"),
+            Red.paint("!")
+        );
+        assert_eq!(nested_line("all › test ! hello", "all", true), expected);
+    }
+
+    #[test]
+    fn nested_line_separator_in_output() {
+        let expected = format!(
+            "{} {} ls | grep x",
+            Fixed(colour_index("all › test › lint")).paint("all › test › lint"),
+            Red.paint("!")
+        );
+        assert_eq!(nested_line("all › test › lint ! ls | grep x", "all", true), expected);
+        assert_eq!(nested_path_end("all › a | b | c", "all"), Some(9));
+        assert_eq!(nested_path_end("all › a b | c", "all"), None);
+        assert_eq!(nested_path_end("all › | x", "all"), None);
+    }
+}
@@ -2,31 +2,60 @@ use std::collections::HashSet;
 use std::env;
 use std::fs;
 use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
-use ansi_term::Colour::Fixed;
+use ansi_term::Colour::{Cyan, Fixed, Yellow};
 use linked_hash_map::LinkedHashMap as Map;
 
 use crate::commands::{Cmd, FileConfig};
 use crate::execute::Run;
+use crate::interpreter::Interpreter;
+use crate::output::Log;
 use crate::utils::{
-    full_path, CliArgs, BAR, DONKEY_COMMAND_ENV, DONKEY_DEPTH_ENV, DONKEY_FILE_ENV, DONKEY_KEEP_ENV, PATH_STR,
+    full_path, CliArgs, Verbosity, BAR, DONKEY_COMMAND_ENV, DONKEY_DEPTH_ENV, DONKEY_FILE_ENV, DONKEY_KEEP_ENV,
+    DONKEY_PREFIX_ENV, DONKEY_SHELL_ENV, DONKEY_VERBOSITY_ENV, PATH_STR,
 };
 
-pub fn main(cmd_name: &str, config: &FileConfig, cmd: &Cmd, cli: &CliArgs, file_path: &PathBuf) -> Result<Run, String> {
+pub fn main(
+    cmd_name: &str,
+    config: &FileConfig,
+    cmd: &Cmd,
+    cli: &CliArgs,
+    cli_args: &[String],
+    file_path: &Path,
+) -> Result<Run, String> {
     let mut path_str: String = PATH_STR.to_string();
     let mut run_depth: i32 = 0;
     if let Ok(v) = env::var(DONKEY_DEPTH_ENV) {
         path_str = format!("{}.{}", PATH_STR, v);
         run_depth = v.parse::<i32>().unwrap_or(1);
     }
+    // other commands are run with "ex:" exactly as given
+    let interpreter = match cmd.smart() || cmd.shebang().is_some() {
+        true => cmd.interpreter(),
+        false => None,
+    };
+    if let Some(i) = interpreter {
+        path_str.push_str(i.extension);
+    }
     let smart_prefix = match env::var(DONKEY_COMMAND_ENV) {
         Ok(c) => format!("{} {} ›", c, cmd_name),
         _ => "»".to_string(),
     };
-    let mut args: Vec<String> = vec![path_str.clone()];
+    let mut args: Vec<String> = Vec::new();
+    let executable = if cmd.shebang().is_some() {
+        // scripts starting with "#!" are executed directly
+        format!("./{}", path_str)
+    } else {
+        if let Some(i) = interpreter {
+            args.extend(i.args.iter().map(ToString::to_string));
+        }
+        args.push(path_str.clone());
+        cmd.executable()
+    };
     args.extend(cmd.args.iter().cloned());
-    args.extend(cli.args.iter().cloned());
+    args.extend(cli_args.iter().cloned());
 
     let mut env: Map<String, String> = Map::new();
     merge_maps(&mut env, &config.env);
@@ -38,8 +67,22 @@ pub fn main(cmd_name: &str, config: &FileConfig, cmd: &Cmd, cli: &CliArgs, file_
         DONKEY_KEEP_ENV.to_string(),
         String::from(if cli.keep_tmp { "1" } else { "0" }),
     );
+    env.insert(DONKEY_VERBOSITY_ENV.to_string(), cli.verbosity.to_string());
+    if let Some(shell) = &cli.shell {
+        env.insert(DONKEY_SHELL_ENV.to_string(), shell.clone());
+    }
+
+    // the path of this command in the tree of nested commands, only set if output is being prefixed
+    let prefix: Option<String> = match env::var(DONKEY_PREFIX_ENV) {
+        Ok(p) => Some(format!("{} › {}", p, cmd_name)),
+        _ if cli.prefix_output => Some(cmd_name.to_string()),
+        _ => None,
+    };
+    if let Some(p) = &prefix {
+        env.insert(DONKEY_PREFIX_ENV.to_string(), p.clone());
+    }
 
-    let working_dir = get_working_dir(&cmd, file_path)?;
+    let working_dir = get_working_dir(cmd, file_path)?;
 
     let watch = match cli.watch_path {
         Some(_) => &cli.watch_path,
@@ -60,103 +103,360 @@ pub fn main(cmd_name: &str, config: &FileConfig, cmd: &Cmd, cli: &CliArgs, file_
         _ => None,
     };
 
+    let log_dir = match cli.log_dir {
+        Some(_) => &cli.log_dir,
+        None => &cmd.log,
+    };
+
+    let log: Option<Log> = match log_dir {
+        Some(dir) => {
+            let mut p = PathBuf::from(dir);
+            if p.is_relative() {
+                p = working_dir.join(&p).to_path_buf();
+            }
+            Some(Log {
+                dir: p,
+                keep: cmd.log_keep,
+            })
+        }
+        _ => None,
+    };
+
     let tmp_path = working_dir.join(&path_str);
-    write(cmd_name, &tmp_path, cmd, &args, &env, config, smart_prefix)?;
+    let echo = cli.verbosity > Verbosity::Quiet;
+    let content = build_script(
+        cmd_name,
+        cmd,
+        &executable,
+        &args,
+        &env,
+        config,
+        file_path,
+        smart_prefix,
+        echo,
+    )?;
+    if cli.dry_run {
+        println!("{}", describe(cmd, &executable, &args, &env, &working_dir));
+        println!("{}", content);
+    } else {
+        if cli.verbosity >= Verbosity::Debug {
+            eprintlnc!(Cyan, "{}", describe(cmd, &executable, &args, &env, &working_dir));
+            eprintln!("{}", content);
+        }
+        write(&tmp_path, &content, cmd.shebang().is_some())?;
+    }
 
     Ok(Run {
         cmd_name: cmd_name.to_string(),
+        executable,
         args,
         env,
         working_dir,
         tmp_path,
         file_path: file_path.to_path_buf(),
         watch_path,
-        print_summary: run_depth == 0,
+        log,
+        prefix,
+        print_summary: match cli.verbosity {
+            Verbosity::Quiet => false,
+            Verbosity::Normal => run_depth == 0,
+            _ => true,
+        },
     })
 }
 
-fn write(
+#[allow(clippy::too_many_arguments)]
+fn build_script(
     cmd_name: &str,
-    path: &PathBuf,
     cmd: &Cmd,
+    executable: &str,
     args: &[String],
     env: &Map<String, String>,
     config: &FileConfig,
+    file_path: &Path,
     smart_prefix: String,
-) -> Result<(), String> {
-    if path.exists() {
-        return err!(
-            "Error writing temporary file:\n  {} already exists, donkey-make may be running already",
-            path.display()
-        );
-    }
-
+    echo: bool,
+) -> Result<String, String> {
     let prefix: Vec<String> = vec![
         String::from(BAR),
         format!(
             "This is a temporary file generated by donkey-make to execute the command: \"{}\"",
             cmd_name
         ),
-        format!("Command to be executed: \"{} {}\"", cmd.executable(), args.join(" ")),
+        format!(
+            "Command to be executed: \"{}\"",
+            format!("{} {}", executable, args.join(" ")).trim_end()
+        ),
         String::from("Environment variables set:"),
-        format!("{:?}", env),
+        format!("{:?}", mask_secrets(env)),
         String::from("This file should only exist very temporarily while it's being executed."),
         String::from(BAR),
     ];
-    let comment = if cmd.executable().starts_with("node") {
-        "//"
-    } else {
-        "#"
-    };
+    let comment = cmd.interpreter().map_or("#", |i| i.comment);
     let sep = format!("\n{} ", comment);
 
-    let script: String = if cmd.smart() {
+    let script: String = if let (true, Some(interpreter)) = (cmd.smart(), cmd.interpreter()) {
         let donk_exe = match env::current_exe() {
             Ok(ex) => full_path(&ex),
             Err(e) => return err!("finding current executable for smart script failed: {}", e),
         };
+        let smart = Smart {
+            interpreter,
+            donk_exe,
+            config,
+            echo,
+            file_path,
+            container: cmd.container.is_some(),
+        };
         let mut cmd_tree: HashSet<String> = HashSet::new();
         cmd_tree.insert((*cmd_name).to_string());
-        build_smart_script(&cmd, smart_prefix, &donk_exe, config, &mut cmd_tree)?
+        let lines = build_smart_script(&smart, cmd_name, cmd_name, cmd, smart_prefix, &mut cmd_tree)?;
+
+        let mut head: Vec<String> = vec![interpreter.prelude.to_string()];
+        let options = cmd.shell_options(&config.settings);
+        // options are set after the trap so "xtrace" doesn't show it being set
+        let options_line = match options.is_empty() {
+            false if interpreter.shell() => Some(format!("set -o {}", options.join(" -o "))),
+            _ => None,
+        };
+        if interpreter.trap_errors {
+            // line numbers in the trap depend on the length of everything before the script, including the
+            // trap itself, so it's built once to count its lines then again with the real numbers
+            let before = prefix.join(&sep).lines().count()
+                + head.join("\n").lines().count()
+                + error_trap(interpreter, &lines, 0).lines().count()
+                + options_line.iter().count();
+            head.push(error_trap(interpreter, &lines, before + 1));
+        }
+        head.extend(options_line);
+        let body: Vec<String> = lines.into_iter().map(|(l, _)| l).collect();
+        format!("{}\n{}", head.join("\n"), body.join("\n"))
     } else {
         cmd.run.join("\n")
     };
 
-    let content = format!("{} {}\n{}", comment, prefix.join(&sep), script);
+    Ok(match cmd.shebang() {
+        // the "#!" line has to stay first
+        Some(shebang) => {
+            let rest = script.split_once('\n').map_or("", |(_, rest)| rest);
+            format!("#!{}\n{} {}\n{}", shebang, comment, prefix.join(&sep), rest)
+        }
+        None => format!("{} {}\n{}", comment, prefix.join(&sep), script),
+    })
+}
 
-    match create_file(path, &content) {
+// function printing the message of the line which failed, "first" is the line number of the script's first line
+fn error_trap(interpreter: &Interpreter, lines: &[(String, Option<String>)], first: usize) -> String {
+    let mut trap: Vec<String> = vec!["__donk_failed() {".to_string(), "  case $1 in".to_string()];
+    let mut line_no = first;
+    for (line, failure) in lines {
+        if let Some(f) = failure {
+            trap.push(format!("    {}) {} ;;", line_no, (interpreter.echo)(f)));
+        }
+        line_no += line.split('\n').count();
+    }
+    trap.push("  esac\n}\ntrap '__donk_failed $LINENO' ERR".to_string());
+    trap.join("\n")
+}
+
+// summary of how the script will be executed, used by --dry-run and -vv
+fn describe(cmd: &Cmd, executable: &str, args: &[String], env: &Map<String, String>, working_dir: &Path) -> String {
+    let env_lines: Vec<String> = mask_secrets(env).iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    let container = match &cmd.container {
+        Some(c) => format!("Container:   {} {}\n", c.engine, c.image),
+        None => String::new(),
+    };
+    format!(
+        "{}Executable:  {}\nArguments:   {}\nWorking dir: {}\nEnvironment:\n  {}\nScript:",
+        container,
+        executable,
+        args.join(" "),
+        working_dir.display(),
+        env_lines.join("\n  ")
+    )
+}
+
+const SECRET_WORDS: [&str; 9] = [
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "PASS",
+    "TOKEN",
+    "KEY",
+    "APIKEY",
+    "CREDENTIALS",
+    "AUTH",
+];
+
+// hide the values of environment variables which look like they contain secrets, eg. "AWS_SECRET_ACCESS_KEY"
+fn mask_secrets(env: &Map<String, String>) -> Map<String, String> {
+    env.iter()
+        .map(|(k, v)| {
+            let upper = k.to_uppercase();
+            if upper.split(['_', '-']).any(|w| SECRET_WORDS.contains(&w)) {
+                (k.clone(), "****".to_string())
+            } else {
+                (k.clone(), v.clone())
+            }
+        })
+        .collect()
+}
+
+fn write(path: &Path, content: &str, executable: bool) -> Result<(), String> {
+    if path.exists() {
+        return err!(
+            "Error writing temporary file:\n  {} already exists, donkey-make may be running already",
+            path.display()
+        );
+    }
+
+    match create_file(path, content, executable) {
         Ok(_) => Ok(()),
         Err(e) => err!("Error writing temporary file {}:\n  {}", path.display(), e),
     }
 }
 
-fn create_file(path: &Path, content: &str) -> std::io::Result<()> {
+fn create_file(path: &Path, content: &str, executable: bool) -> std::io::Result<()> {
     let mut f = fs::File::create(path)?;
     f.write_all(content.as_bytes())?;
+    if executable {
+        f.set_permissions(fs::Permissions::from_mode(0o755))?;
+    }
     Ok(())
 }
 
-const NO_ECHO_PREFIX: char = '_';
-const DONK_PREFIX: char = '+';
-const INLINE_PREFIX: char = '<';
+pub const NO_ECHO_PREFIX: char = '_';
+pub const DONK_PREFIX: char = '+';
+pub const INLINE_PREFIX: char = '<';
 const PREFIXES: [char; 3] = [NO_ECHO_PREFIX, DONK_PREFIX, INLINE_PREFIX];
 
+// shared by a smart script and the inline sub-commands included in it
+struct Smart<'a> {
+    interpreter: &'static Interpreter,
+    donk_exe: String,
+    config: &'a FileConfig,
+    echo: bool,
+    file_path: &'a Path,
+    // the script runs in a container where donk isn't available
+    container: bool,
+}
+
+impl<'a> Smart<'a> {
+    // line in the config file of each line of a command's script, found by searching forward from the
+    // command's "run" key, lines which can't be found (eg. because of escapes or in a one line list) are
+    // reported at the "run" key
+    fn source_lines(&self, cmd_name: &str, lines: &[(usize, &str)]) -> Vec<usize> {
+        let start = self.config.line(cmd_name).unwrap_or(1);
+        let source: Vec<&str> = self.config.source.lines().collect();
+        let end = self
+            .config
+            .lines
+            .values()
+            .cloned()
+            .filter(|&l| l > start)
+            .min()
+            .map_or(source.len(), |e| e - 1)
+            .min(source.len());
+        // skip keys like "description:" which might contain the same text as the script
+        let run_key = (start - 1..end).find(|&i| is_run_key(source[i]));
+        let fallback = run_key.map_or(start, |i| i + 1);
+        let mut next = run_key.unwrap_or(start - 1);
+        lines
+            .iter()
+            .map(|(_, line)| {
+                let line = line.trim();
+                match (next..end).find(|&i| !line.is_empty() && source_matches(source[i], line)) {
+                    Some(found) => {
+                        next = found + 1;
+                        found + 1
+                    }
+                    None => fallback,
+                }
+            })
+            .collect()
+    }
+
+    // message printed when a line fails, eg. 'foo run[1] failed (donk.yml:4): make build'
+    fn failure(&self, path: &str, index: usize, source_line: usize, line: &str) -> String {
+        let message = format!(
+            "{} run[{}] failed ({}:{}): {}",
+            path,
+            index,
+            self.file_path.display(),
+            source_line,
+            line
+        );
+        epaint!(Yellow, message)
+    }
+}
+
+// "run:" in YAML, "run =" in TOML or "\"run\":" in JSON
+fn is_run_key(source: &str) -> bool {
+    let trimmed = source.trim_start().trim_start_matches(['"', '\'']);
+    let rest = trimmed
+        .strip_prefix("run")
+        .map(|r| r.trim_start_matches(['"', '\'']).trim_start());
+    matches!(rest, Some(r) if r.starts_with(':') || r.starts_with('='))
+}
+
+// whether a line of the config file is a line of the script, eg. "- make", "run: make" or "\"make\","
+fn source_matches(source: &str, line: &str) -> bool {
+    let value = source.trim().trim_end_matches(',');
+    let value = value.strip_prefix("- ").unwrap_or(value);
+    let key_value = [": ", " = "].iter().find_map(|sep| match value.split_once(sep) {
+        Some((key, rest)) if !key.trim_matches(['"', '\'']).contains(char::is_whitespace) => Some(rest),
+        _ => None,
+    });
+    [Some(value), key_value]
+        .iter()
+        .flatten()
+        .any(|v| unquote(v.trim()) == line)
+}
+
+fn unquote(s: &str) -> &str {
+    match s.chars().next() {
+        Some(q) if (q == '"' || q == '\'') && s.len() > 1 && s.ends_with(q) => &s[1..s.len() - 1],
+        _ => s,
+    }
+}
+
+// lines of the script, with the message to print if the line fails for lines from "run"
 fn build_smart_script(
+    smart: &Smart,
+    cmd_name: &str,
+    // names of the command and the commands it's included in, eg. "build › lint"
+    path: &str,
     cmd: &Cmd,
     smart_prefix: String,
-    donk_exe: &str,
-    config: &FileConfig,
     cmd_tree: &mut HashSet<String>,
-) -> Result<String, String> {
-    let all = cmd.run.join("\n");
-    let lines: Vec<&str> = all.split('\n').collect();
+) -> Result<Vec<(String, Option<String>)>, String> {
+    let interpreter = smart.interpreter;
+    let lines: Vec<(usize, &str)> = cmd
+        .run
+        .iter()
+        .enumerate()
+        .flat_map(|(index, entry)| entry.split('\n').map(move |line| (index, line)))
+        .collect();
     let len = lines.len();
+    let source_lines = smart.source_lines(cmd_name, &lines);
 
-    let mut script: Vec<String> = vec!["set -e".to_string()];
-    for line in lines {
-        if !PREFIXES.iter().any(|&prefix| line.starts_with(prefix)) {
-            let coloured = epaint!(Fixed(205), format!("{} {}", smart_prefix, line));
-            script.push(format!(">&2 echo '{}'", coloured));
+    // statement printing a line of the script, lines starting with a prefix aren't printed
+    let echo = |line: &str| -> Option<(String, Option<String>)> {
+        if !smart.echo || PREFIXES.iter().any(|&prefix| line.starts_with(prefix)) {
+            return None;
+        }
+        let coloured = epaint!(Fixed(205), format!("{} {}", smart_prefix, line));
+        Some(((interpreter.echo)(&coloured), None))
+    };
+    let mut script: Vec<(String, Option<String>)> = Vec::with_capacity(len * 2);
+    if !interpreter.shell() {
+        // other languages don't allow a statement on every line, eg. inside a multi-line string, so the
+        // lines are all printed before the script runs
+        script.extend(lines.iter().filter_map(|(_, line)| echo(line)));
+    }
+    for ((index, line), source_line) in lines.into_iter().zip(source_lines) {
+        if interpreter.shell() {
+            script.extend(echo(line));
         }
 
         let mut ex_line = if line.starts_with(NO_ECHO_PREFIX) {
@@ -166,40 +466,59 @@ fn build_smart_script(
         };
 
         if ex_line.starts_with(INLINE_PREFIX) {
-            let sub_cmd_name = &ex_line[1..].trim().to_string();
+            let (sub_cmd_name, sub_cmd) = get_sub_command(smart.config, ex_line[1..].trim(), cmd)?;
             if cmd_tree.contains(sub_cmd_name) {
                 return err!(
                     "Command \"{}\" reused in an inline sub-command, this would cause infinite recursion",
                     sub_cmd_name
                 );
             }
-            cmd_tree.insert(sub_cmd_name.clone().to_string());
-            let sub_cmd = get_sub_command(config, sub_cmd_name)?;
+            cmd_tree.insert(sub_cmd_name.clone());
             let sub_cmd_prefix = format!("{} {} ›", smart_prefix, sub_cmd_name);
-            ex_line = build_smart_script(sub_cmd, sub_cmd_prefix, donk_exe, config, &mut *cmd_tree)?;
+            let sub_path = format!("{} › {}", path, sub_cmd_name);
+            script.extend(build_smart_script(
+                smart,
+                sub_cmd_name,
+                &sub_path,
+                sub_cmd,
+                sub_cmd_prefix,
+                &mut *cmd_tree,
+            )?);
+            continue;
         } else {
-            if len == 1 && !line.contains('$') {
+            if let (1, false, Some(pass_args)) = (len, line.contains('$'), interpreter.pass_args) {
                 // must be the first line
-                ex_line = format!("{} $@", line)
+                ex_line = format!("{} {}", ex_line, pass_args)
             }
-            if ex_line.starts_with(DONK_PREFIX) {
-                ex_line = format!("{} {}", donk_exe, &ex_line[1..]);
+            if let Some(call) = ex_line.strip_prefix(DONK_PREFIX) {
+                if smart.container {
+                    return err!(
+                        "\"{}\" can't be used in a command run in a container, donk isn't available in the container",
+                        line.trim()
+                    );
+                }
+                ex_line = (interpreter.call)(&smart.donk_exe, call)?;
             }
         }
-        script.push(ex_line);
+        let failure = match line.trim() {
+            "" => None,
+            _ => Some(smart.failure(path, index, source_line, line)),
+        };
+        script.push((ex_line, failure));
     }
-    Ok(script.join("\n"))
+    Ok(script)
 }
 
-fn get_sub_command<'a>(config: &'a FileConfig, cmd_name: &str) -> Result<&'a Cmd, String> {
-    match config.commands.get(cmd_name) {
-        Some(c) => {
-            if c.smart() {
-                Ok(c)
+fn get_sub_command<'a>(config: &'a FileConfig, cmd_name: &str, parent: &Cmd) -> Result<(&'a String, &'a Cmd), String> {
+    match config.get(cmd_name) {
+        Some((name, c)) => {
+            if c.inlinable_in(parent) {
+                Ok((name, c))
             } else {
                 err!(
-                    "Sub-command \"{}\" not a bash-smart script, remove \"ex:\" or use '{}' not '{}'",
+                    "Sub-command \"{}\" not a {} script, remove \"ex:\" or use '{}' not '{}'",
                     cmd_name,
+                    parent.ex(),
                     DONK_PREFIX,
                     INLINE_PREFIX
                 )
@@ -217,7 +536,7 @@ fn merge_maps(base: &mut Map<String, String>, update: &Map<String, String>) {
     base.extend(update.iter().map(|(k, v)| (k.clone(), v.clone())));
 }
 
-fn get_working_dir(cmd: &Cmd, file_path: &PathBuf) -> Result<PathBuf, String> {
+pub fn get_working_dir(cmd: &Cmd, file_path: &Path) -> Result<PathBuf, String> {
     match &cmd.working_dir {
         Some(wd) => {
             let mut path = PathBuf::from(&wd);
@@ -226,7 +545,7 @@ fn get_working_dir(cmd: &Cmd, file_path: &PathBuf) -> Result<PathBuf, String> {
                     Some(p) => p,
                     _ => return err!("\"{}\" path appears to have no parent directory", file_path.display()),
                 };
-                path = file_dir.join(&wd).to_path_buf();
+                path = file_dir.join(wd).to_path_buf();
             }
             if !path.is_dir() {
                 err!("\"{}\" is not a directory", &wd)
@@ -259,6 +578,19 @@ mod tests {
         assert_eq!(format!("{:?}", base), r#"{"a": "b", "c": "d"}"#);
     }
 
+    #[test]
+    fn mask_secrets_words() {
+        let mut env: Map<String, String> = Map::new();
+        env.insert("DONKEY_MAKE_KEEP".to_string(), "0".to_string());
+        env.insert("AWS_SECRET_ACCESS_KEY".to_string(), "abc".to_string());
+        env.insert("github_token".to_string(), "def".to_string());
+        env.insert("KEYBOARD".to_string(), "qwerty".to_string());
+        assert_eq!(
+            format!("{:?}", mask_secrets(&env)),
+            r#"{"DONKEY_MAKE_KEEP": "0", "AWS_SECRET_ACCESS_KEY": "****", "github_token": "****", "KEYBOARD": "qwerty"}"#
+        );
+    }
+
     #[test]
     fn merge_update() {
         let mut base: Map<String, String> = Map::new();
@@ -268,4 +600,18 @@ mod tests {
         merge_maps(&mut base, &update);
         assert_eq!(format!("{:?}", base), r#"{"a": "d"}"#);
     }
+
+    #[test]
+    fn source_lines_match() {
+        assert!(source_matches("  - make build", "make build"));
+        assert!(source_matches("foo: 'echo \"x\"'", "echo \"x\""));
+        assert!(source_matches("    x = 1", "x = 1"));
+        assert!(source_matches("  run = \"make\"", "make"));
+        assert!(source_matches("    \"make\",", "make"));
+        assert!(!source_matches("  description: make things", "make"));
+        assert!(!source_matches("  - make build", "make"));
+        assert!(is_run_key("  run: make"));
+        assert!(is_run_key("  \"run\": [\"make\"]"));
+        assert!(!is_run_key("  runner: make"));
+    }
 }
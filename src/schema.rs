@@ -0,0 +1,70 @@
+use serde_json::{json, Map, Value};
+
+use crate::commands::{Key, CMD_KEYS, RUN_SCHEMA, SETTINGS_KEYS};
+
+// JSON Schema for config files, for use by editors with YAML language servers
+pub fn main() -> String {
+    let schema = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "donkey-make config",
+        "description": "Commands for donkey-make, see https://github.com/samuelcolvin/donkey-make",
+        "type": "object",
+        "properties": {
+            ".env": {
+                "description": "Environment variables set when running all commands",
+                "type": "object",
+                "additionalProperties": {"type": "string"},
+            },
+            ".settings": {
+                "description": "Settings which apply to the whole file",
+                "type": "object",
+                "properties": properties(&SETTINGS_KEYS),
+                "additionalProperties": false,
+            },
+        },
+        "additionalProperties": {"$ref": "#/definitions/command"},
+        "definitions": {
+            "command": {
+                "oneOf": [
+                    parse(RUN_SCHEMA),
+                    {
+                        "type": "object",
+                        "properties": properties(&CMD_KEYS),
+                        "required": ["run"],
+                        "additionalProperties": false,
+                    },
+                ],
+            },
+        },
+    });
+    serde_json::to_string_pretty(&schema).expect("Unable to serialize schema")
+}
+
+fn properties(keys: &[Key]) -> Value {
+    let mut props = Map::new();
+    for key in keys {
+        let mut schema = parse(key.schema);
+        if let Value::Object(m) = &mut schema {
+            m.insert("description".to_string(), Value::String(key.description.to_string()));
+        }
+        props.insert(key.name.to_string(), schema);
+    }
+    Value::Object(props)
+}
+
+fn parse(schema: &str) -> Value {
+    serde_json::from_str(schema).expect("Invalid key schema")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_valid_json() {
+        let schema: Value = serde_json::from_str(&main()).unwrap();
+        let cmd_props = &schema["definitions"]["command"]["oneOf"][1]["properties"];
+        assert_eq!(cmd_props.as_object().unwrap().len(), CMD_KEYS.len());
+        assert_eq!(cmd_props["log_keep"]["minimum"], 1);
+    }
+}
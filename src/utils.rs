@@ -1,11 +1,18 @@
-use std::path::PathBuf;
+use std::env;
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ansi_term::Colour::Cyan;
 
-pub const BASH_SMART: &str = "bash-smart";
 pub const BASH: &str = "bash";
 pub const DONKEY_DEPTH_ENV: &str = "DONKEY_MAKE_DEPTH";
 pub const DONKEY_FILE_ENV: &str = "DONKEY_MAKE_CONFIG_FILE";
 pub const DONKEY_COMMAND_ENV: &str = "DONKEY_MAKE_COMMAND";
 pub const DONKEY_KEEP_ENV: &str = "DONKEY_MAKE_KEEP";
+pub const DONKEY_PREFIX_ENV: &str = "DONKEY_MAKE_PREFIX";
+pub const DONKEY_VERBOSITY_ENV: &str = "DONKEY_MAKE_VERBOSITY";
+pub const DONKEY_SHELL_ENV: &str = "DONKEY_MAKE_SHELL";
 pub const PATH_STR: &str = ".donk.tmp";
 pub const BAR: &str = "==========================================================================================";
 
@@ -14,13 +21,130 @@ pub struct CliArgs {
     pub file_path: Option<String>,
     pub command: Option<String>,
     pub args: Vec<String>,
+    // number of args at the end of "args" which came after "--", these always go to the last command
+    pub trailing_args: usize,
+    pub keep_going: bool,
     pub keep_tmp: bool,
     pub watch_path: Option<String>,
+    pub log_dir: Option<String>,
+    pub prefix_output: bool,
+    pub verbosity: Verbosity,
+    // overrides .settings.shell, passed on to nested commands
+    pub shell: Option<String>,
+    pub dry_run: bool,
+    pub explain: bool,
+    pub check: bool,
+    pub schema: bool,
+    pub list: bool,
+    // format for --list, one of "plain", "json" or "yaml"
+    pub format: String,
+    pub all: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    // no lines echoed and no summaries
+    Quiet,
+    // lines echoed and summaries for the top level command
+    Normal,
+    // lines echoed and summaries for nested commands too
+    Verbose,
+    // as Verbose, plus the environment and generated script are printed before execution
+    Debug,
 }
 
-pub fn full_path(path: &PathBuf) -> String {
+impl Verbosity {
+    pub fn from_env() -> Verbosity {
+        match env::var(DONKEY_VERBOSITY_ENV).as_ref().map(String::as_str) {
+            Ok("0") => Verbosity::Quiet,
+            Ok("2") => Verbosity::Verbose,
+            Ok("3") => Verbosity::Debug,
+            _ => Verbosity::Normal,
+        }
+    }
+}
+
+impl fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let v = match self {
+            Verbosity::Quiet => 0,
+            Verbosity::Normal => 1,
+            Verbosity::Verbose => 2,
+            Verbosity::Debug => 3,
+        };
+        write!(f, "{}", v)
+    }
+}
+
+pub fn full_path(path: &Path) -> String {
     match path.canonicalize() {
         Ok(p) => p.to_string_lossy().to_string(),
         _ => path.to_string_lossy().to_string(),
     }
 }
+
+// the most similar of possibilities to v, if any are similar enough
+pub fn closest<'a>(v: &str, possibilities: &'a [String]) -> Option<&'a String> {
+    let mut threshold: f64 = 0.8;
+    let mut candidate: Option<&String> = None;
+    for pv in possibilities {
+        let confidence = strsim::jaro_winkler(v, pv);
+        if confidence > threshold {
+            threshold = confidence;
+            candidate = Some(pv);
+        }
+    }
+    candidate
+}
+
+pub fn suggestion(v: &str, possibilities: &[String]) -> String {
+    match closest(v, possibilities) {
+        Some(c) => paint!(Cyan, format!("\n\n    perhaps you meant \"{}\"?", c)),
+        _ => "".to_string(),
+    }
+}
+
+// current UTC time formatted for use in file names, eg. "2019-06-02T18-04-59.123"
+pub fn timestamp() -> String {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format_timestamp(since_epoch.as_secs(), since_epoch.subsec_millis())
+}
+
+fn format_timestamp(secs: u64, millis: u32) -> String {
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let sod = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}-{:02}-{:02}.{:03}",
+        year,
+        month,
+        day,
+        sod / 3600,
+        (sod % 3600) / 60,
+        sod % 60,
+        millis
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_timestamp_epoch() {
+        assert_eq!(format_timestamp(0, 0), "1970-01-01T00-00-00.000");
+    }
+
+    #[test]
+    fn format_timestamp_leap_year() {
+        assert_eq!(format_timestamp(1_582_977_845, 42), "2020-02-29T12-04-05.042");
+    }
+}
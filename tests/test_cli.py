@@ -2,7 +2,9 @@ import json
 import os
 import re
 import signal
+import subprocess
 import threading
+from subprocess import PIPE
 from time import sleep
 
 from psutil import Process
@@ -57,6 +59,20 @@ def test_smart_script(run, test_path: TPath):
     )
 
 
+def test_smart_one_line_no_echo_args(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+    - _echo hello
+    """)
+    p = run('foo', 'world')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'hello world\n'
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stderr) == (
+        'Running command "foo" from donkey-make.yaml...\n'
+        'Command "foo" successful in XXms 👍\n'
+    )
+
+
 def test_tmp_exists(run, test_path: TPath):
     test_path.write_file('.donk.tmp', '.')
     test_path.write_file('donkey-make.yaml', 'foo: xx')
@@ -75,7 +91,7 @@ def test_no_config_exists(run, test_path):
     assert p.stdout == ''
     assert p.stderr == (
         'No commands config file provided, and no default found, tried:\n'
-        '  donk.ya?ml, donkey.ya?ml and donkey-make.ya?ml\n'
+        '  donk.ya?ml, donkey.ya?ml, donkey-make.ya?ml, donk.toml and donk.json\n'
     )
 
 
@@ -90,6 +106,46 @@ def test_invalid_yaml(run, test_path: TPath):
     )
 
 
+def test_toml(run, test_path: TPath):
+    test_path.write_file('donk.toml', """
+bar = "echo bar"
+
+[".env"]
+GREETING = "hello"
+
+[foo]
+run = ["echo $GREETING", "+bar"]
+description = "this is foo"
+""")
+    p = run()
+    assert p.returncode == 0, p.stderr
+    assert re.sub(r'v[\d.]+', 'v0.0.0', p.stdout) == (
+        'donkey-make v0.0.0, commands available from donk.toml:\n'
+        '  bar            (1 line) echo bar\n'
+        '  foo            (2 lines) this is foo\n'
+    )
+    p = run('foo')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'hello\nbar\n'
+
+
+def test_json(run, test_path: TPath):
+    test_path.write_file('donk.json', json.dumps({'foo': {'run': ['echo json'], 'env': {'x': 'y'}}}))
+    p = run('foo')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'json\n'
+
+
+def test_check_json(run, test_path: TPath):
+    test_path.write_file('donk.json', '{\n  "foo": {\n    "run": "echo x",\n    "ex": 3\n  }\n}\n')
+    p = run('--check')
+    assert p.returncode == 1
+    assert p.stdout == (
+        'donk.json:4:5: foo.ex: invalid type: integer `3`, expected a string\n'
+        '1 problem found in donk.json\n'
+    )
+
+
 def test_subcommands(run, test_path: TPath):
     test_path.write_file('donkey-make.yaml', """
     a:
@@ -159,6 +215,7 @@ def test_extra_env(run, test_path: TPath):
         'DONKEY_MAKE_CONFIG_FILE': '{}/donkey-make.yaml'.format(test_path.path),
         'DONKEY_MAKE_DEPTH': '2',
         'DONKEY_MAKE_KEEP': '0',
+        'DONKEY_MAKE_VERBOSITY': '1',
     }
 
 
@@ -242,6 +299,440 @@ def test_inline_not_smart(run, test_path: TPath):
     )
 
 
+def test_python_smart(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      ex: python-smart
+      run:
+      - print('args', sys.argv[1:])
+      - +bar x
+      - <spam
+    bar:
+    - echo "bar $1"
+    spam:
+      ex: python3-smart
+      run: _print("it's spam")
+    """)
+    p = run('foo', 'a')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == "args ['a']\nbar x\nit's spam\n"
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stderr) == (
+        'Running command "foo" from donkey-make.yaml...\n'
+        "» print('args', sys.argv[1:])\n"
+        '» bar › echo "bar $1"\n'
+        'Command "foo" successful in XXms 👍\n'
+    )
+
+
+def test_smart_call_quoted_args(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      ex: node-smart
+      run: +bar 'hello world' "it's" x
+    bar:
+    - _echo "$#|$1|$2|$3"
+    """)
+    p = run('foo')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == "3|hello world|it's|x\n"
+
+
+def test_interpreter_args_smart_only(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    plain:
+      ex: deno
+      run: console.log(1)
+    smart:
+      ex: deno-smart
+      run: console.log(1)
+    """)
+    p = run('--dry-run', 'plain')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout.startswith('Executable:  deno\nArguments:   .donk.tmp\n')
+    p = run('--dry-run', 'smart')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout.startswith('Executable:  deno\nArguments:   run --allow-all .donk.tmp.ts\n')
+
+
+def test_python_smart_blocks(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      ex: python-smart
+      run: |
+        def double(x):
+            return x * 2
+        for i in range(2):
+            if i:
+                print('odd', double(i))
+            else:
+                print('even', double(i))
+        text = '''
+        a
+        '''
+        print(text.strip())
+    """)
+    p = run('foo')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == "even 0\nodd 2\na\n"
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stderr) == (
+        'Running command "foo" from donkey-make.yaml...\n'
+        '» def double(x):\n'
+        '»     return x * 2\n'
+        '» for i in range(2):\n'
+        '»     if i:\n'
+        "»         print('odd', double(i))\n"
+        '»     else:\n'
+        "»         print('even', double(i))\n"
+        "» text = '''\n"
+        '» a\n'
+        "» '''\n"
+        '» print(text.strip())\n'
+        '» \n'
+        'Command "foo" successful in XXms 👍\n'
+    )
+
+
+def test_node_smart_template_literal(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      ex: node-smart
+      run: |
+        const text = `a
+        b`
+        console.log(text.split('\\n').join('|'))
+    """)
+    p = run('foo')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'a|b\n'
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stderr) == (
+        'Running command "foo" from donkey-make.yaml...\n'
+        '» const text = `a\n'
+        '» b`\n'
+        "» console.log(text.split('\\n').join('|'))\n"
+        '» \n'
+        'Command "foo" successful in XXms 👍\n'
+    )
+
+
+def test_python_smart_fails(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      ex: python-smart
+      run:
+      - +bar
+      - print('not run')
+    bar: exit 4
+    """)
+    p = run('foo')
+    assert p.returncode == 4
+    assert p.stdout == ''
+
+
+def test_smart_unsupported(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      ex: perl-smart
+      run: print 1
+    """)
+    p = run('foo')
+    assert p.returncode == 100
+    assert p.stderr == (
+        'Error parsing donkey-make.yaml:\n'
+        '  smart scripts are not supported for "perl", interpreters supported are: '
+        'bash, sh, zsh, python, node, ruby, deno, pwsh at line 2 column 8\n'
+    )
+
+
+def test_inline_other_interpreter(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      ex: python-smart
+      run: <bar
+    bar: echo bar
+    """)
+    p = run('foo')
+    assert p.returncode == 100
+    assert p.stderr == """Sub-command "bar" not a python-smart script, remove "ex:" or use '+' not '<'\n"""
+
+
+def test_shebang(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo: |
+      #!/usr/bin/env python3
+      import sys
+      print('args', sys.argv[1:])
+    """)
+    p = run('foo', 'a', 'b')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == "args ['a', 'b']\n"
+    p = run('--dry-run', 'foo')
+    assert p.returncode == 0, p.stderr
+    head, script = p.stdout.split('Script:\n')
+    assert head.startswith('Executable:  ./.donk.tmp\nArguments:   \n')
+    assert script.startswith('#!/usr/bin/env python3\n# =====')
+    assert script.endswith("\nimport sys\nprint('args', sys.argv[1:])\n\n")
+
+
+SHELL_CONFIG = """
+.settings:
+  shell: sh
+foo:
+- echo "foo ${BASH_VERSION:-none} $1"
+- +bar
+bar: echo "bar ${BASH_VERSION:-none}"
+"""
+
+
+def test_settings_shell(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', SHELL_CONFIG)
+    p = run('foo', 'x')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'foo none x\nbar none\n'
+    p = run('--dry-run', 'foo')
+    assert p.stdout.startswith('Executable:  sh\n')
+    assert ">&2 printf '%s\\n' '» echo \"foo ${BASH_VERSION:-none} $1\"'\n" in p.stdout
+
+
+def test_shell_option(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', SHELL_CONFIG)
+    p = run('--shell', 'bash', 'foo')
+    assert p.returncode == 0, p.stderr
+    # the shell is also used by nested commands
+    assert re.fullmatch(r'foo \d\S+ \nbar \d\S+\n', p.stdout), p.stdout
+
+
+def test_shell_invalid(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', SHELL_CONFIG.replace('shell: sh', 'shell: fish'))
+    p = run('foo')
+    assert p.returncode == 100
+    assert p.stderr == (
+        'Error parsing donkey-make.yaml:\n'
+        '  .settings: shell "fish" not supported, it should be bash, sh, zsh or the path to one of them\n'
+    )
+
+
+def test_strict(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    .settings:
+      strict: true
+    foo:
+    - false | true
+    - echo "not run"
+    bar:
+      run: echo "bar ${UNSET_VAR:-default} $UNSET_VAR"
+      shell_options:
+      - xtrace
+      strict: false
+    """)
+    p = run('foo')
+    assert p.returncode == 1
+    assert p.stdout == ''
+    p = run('--dry-run', 'foo')
+    assert "\ntrap '__donk_failed $LINENO' ERR\nset -o pipefail -o nounset\n" in p.stdout
+    p = run('-q', 'bar')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'bar default \n'
+    assert p.stderr == '+ echo \'bar default \'\n'
+
+
+def test_shell_options_not_shell(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run: print(1)
+      ex: python-smart
+      strict: true
+    """)
+    p = run('foo')
+    assert p.returncode == 100
+    assert p.stderr == (
+        'Error parsing donkey-make.yaml:\n'
+        '  shell_options and strict can only be used with smart scripts run by a shell at line 2 column 8\n'
+    )
+
+
+def test_strict_sh(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    .settings:
+      shell: sh
+      strict: true
+    foo: echo foo
+    """)
+    p = run('foo')
+    assert p.returncode == 100
+    assert p.stderr == (
+        'Error parsing donkey-make.yaml:\n'
+        '  command "foo" on line 5: strict can\'t be used with sh as it doesn\'t support "pipefail", '
+        'use "shell_options: [nounset]"\n'
+    )
+    p = run('--check')
+    assert p.returncode == 1
+    assert p.stdout.startswith('donkey-make.yaml:5:1: foo: strict can\'t be used with sh')
+
+
+def test_shell_options_sh(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    .settings:
+      strict: true
+    foo:
+      run: echo "foo ${UNSET_VAR:-default}"
+      shell_options: [nounset]
+      strict: false
+    """)
+    p = run('--shell', 'sh', 'foo')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'foo default\n'
+    p = run('--shell', 'sh', '--dry-run', 'foo')
+    assert '\nset -o nounset\n' in p.stdout
+    test_path.write_file('donkey-make.yaml', """
+    bar:
+      run: echo bar
+      ex: sh-smart
+      shell_options: [pipefail]
+    """)
+    p = run('bar')
+    assert p.returncode == 100
+    assert p.stderr == (
+        'Error parsing donkey-make.yaml:\n'
+        '  command "bar" on line 2: shell option "pipefail" isn\'t supported by sh, options supported are: '
+        'allexport, errexit, ignoreeof, monitor, noclobber, noexec, noglob, nolog, notify, nounset, verbose, vi, '
+        'xtrace\n'
+    )
+
+
+def test_failed_line(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+    - echo foo
+    - <bar
+    - echo never
+    bar:
+      run:
+      - _echo bar
+      - |
+        test -n x
+        test -f missing
+    """)
+    p = run('foo')
+    assert p.returncode == 1
+    assert p.stdout == 'foo\nbar\n'
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stderr) == (
+        'Running command "foo" from donkey-make.yaml...\n'
+        '» echo foo\n'
+        '» bar › test -n x\n'
+        '» bar › test -f missing\n'
+        'foo › bar run[1] failed (donkey-make.yaml:11): test -f missing\n'
+        'Command "foo" failed in XXms, exit code 1 👎\n'
+    )
+
+
+def test_failed_line_description(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      description: test -f missing fails
+      strict: true
+      run:
+      - test -n x
+      - test -f missing
+    """)
+    p = run('-q', 'foo')
+    assert p.returncode == 1
+    assert p.stderr == 'foo run[1] failed (donkey-make.yaml:7): test -f missing\n'
+
+
+def test_failed_line_one_line_list(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      description: the foo
+      run: [test -n x, test -f missing]
+    """)
+    p = run('-q', 'foo')
+    assert p.returncode == 1
+    assert p.stderr == 'foo run[1] failed (donkey-make.yaml:4): test -f missing\n'
+
+
+def test_retries(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run:
+      - _echo x >> count.txt
+      - test $(wc -l < count.txt) -ge 3
+      retries: 3
+      retry_delay: 0.01
+      retry_backoff: 2
+    """)
+    p = run('foo')
+    assert p.returncode == 0, p.stderr
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stderr) == (
+        'Running command "foo" from donkey-make.yaml...\n'
+        '» test $(wc -l < count.txt) -ge 3\n'
+        'foo run[1] failed (donkey-make.yaml:5): test $(wc -l < count.txt) -ge 3\n'
+        'Attempt 1 of command "foo" failed in XXms, exit code 1, retrying in XXms...\n'
+        '» test $(wc -l < count.txt) -ge 3\n'
+        'foo run[1] failed (donkey-make.yaml:5): test $(wc -l < count.txt) -ge 3\n'
+        'Attempt 2 of command "foo" failed in XXms, exit code 1, retrying in XXms...\n'
+        '» test $(wc -l < count.txt) -ge 3\n'
+        'Command "foo" successful in XXms after 3 attempts 👍\n'
+    )
+
+
+def test_retries_exit_codes(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run: exit 4
+      retries: 2
+      retry_delay: 0
+      retry_on_exit_codes: [3]
+    bar:
+      run: exit 3
+      retries: 2
+      retry_delay: 0
+      retry_on_exit_codes: [3]
+    """)
+    p = run('foo')
+    assert p.returncode == 4
+    assert 'Attempt' not in p.stderr
+    p = run('bar')
+    assert p.returncode == 3
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stderr).endswith(
+        'Attempt 2 of command "bar" failed in XXms, exit code 3, retrying in XXms...\n'
+        '» exit 3\n'
+        'Command "bar" failed in XXms after 3 attempts, exit code 3 👎\n'
+    )
+
+
+def test_retries_quiet_log(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run: _echo "attempt" && exit 3
+      retries: 1
+      retry_delay: 0.001
+      retry_backoff: 1e30
+      log: logs
+    """)
+    p = run('-q', 'foo')
+    assert p.returncode == 3
+    assert p.stdout == 'attempt\nattempt\n'
+    assert p.stderr == ''
+    logs = list((test_path.path / 'logs').iterdir())
+    assert len(logs) == 1
+    content = logs[0].read_text()
+    assert content.count('attempt\n') == 2
+    assert len(re.findall(r'^# exit code 3 after', content, flags=re.M)) == 2
+
+
+def test_retries_invalid(run, test_path: TPath):
+    for key, value in (('retry_backoff', '.nan'), ('retry_delay', '.inf')):
+        test_path.write_file('donkey-make.yaml', """
+    foo:
+      run: exit 3
+      retries: 1
+      {}: {}
+    """.format(key, value))
+        p = run('foo')
+        assert p.returncode == 100
+        assert '{} must be a number greater than or equal to'.format(key) in p.stderr
+
+
 def test_no_suggestion(run, test_path: TPath):
     test_path.write_file('donkey-make.yaml', """
     foo:
@@ -272,6 +763,39 @@ def test_suggestion(run, test_path: TPath):
     )
 
 
+def test_unknown_key(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run: echo testing
+      watch_debouce: 1
+    """)
+    p = run('foo')
+    assert p.returncode == 100
+    assert p.stdout == ''
+    assert p.stderr == (
+        'Error parsing donkey-make.yaml:\n'
+        '  unknown key "watch_debouce" in command "foo" on line 2, allowed keys are:\n'
+        '  run, args, env, working_dir, watch, watch_debounce, log, log_keep, ex, description, group, hidden, '
+        'aliases, complete, container, shell_options, strict, retries, retry_delay, retry_backoff, '
+        'retry_on_exit_codes\n'
+        '\n'
+        '    perhaps you meant "watch_debounce"?\n'
+    )
+
+
+def test_unknown_key_allowed(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    .settings:
+      allow_unknown_keys: true
+    foo:
+      run: echo testing
+      watch_debouce: 1
+    """)
+    p = run('foo')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'testing\n'
+
+
 def test_working_dir(run, test_path: TPath):
     test_path.write_file('donkey-make.yaml', """
     foo:
@@ -289,6 +813,778 @@ def test_working_dir(run, test_path: TPath):
     )
 
 
+def test_log_file(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run:
+        - _echo this is output
+        - _true
+      log: logs
+      log_keep: 2
+    """)
+    for _ in range(3):
+        p = run('foo')
+        assert p.returncode == 0, (p.stdout, p.stderr)
+        assert p.stdout == 'this is output\n'
+    log_files = sorted((test_path.path / 'logs').iterdir())
+    assert len(log_files) == 2
+    assert all(re.fullmatch(r'foo\.\d{4}-\d\d-\d\dT\d\d-\d\d-\d\d\.\d{3}\.log', f.name) for f in log_files)
+    log = re.sub(r'started at \S+', 'started at [TIME]', log_files[-1].read_text())
+    assert re.sub(r'[\d.]+ms', 'XXms', log) == (
+        '# donkey-make log for command "foo" started at [TIME] in {}\n'
+        'this is output\n'
+        '# exit code 0 after XXms\n'
+    ).format(test_path.path.resolve())
+
+
+def test_log_dir_cli(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run:
+        - _echo this is output
+        - exit 3
+    """)
+    p = run('--log-dir', 'other', 'foo')
+    assert p.returncode == 3
+    assert p.stdout == 'this is output\n'
+    (log_file,) = (test_path.path / 'other').iterdir()
+    log_lines = log_file.read_text().split('\n')
+    assert log_lines[0].startswith('# donkey-make log for command "foo" started at ')
+    assert sorted(log_lines[1:3]) == ['this is output', '» exit 3']
+    assert re.sub(r'[\d.]+ms', 'XXms', log_lines[3]) == '# exit code 3 after XXms'
+
+
+def test_log_keep_invalid(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run: echo x
+      log: logs
+      log_keep: 0
+    """)
+    p = run('foo')
+    assert p.returncode == 100
+    assert p.stdout == ''
+    assert p.stderr == (
+        'Error parsing donkey-make.yaml:\n'
+        '  log_keep must be greater than 0 at line 2 column 8\n'
+    )
+
+
+def test_prefix_output(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    all:
+    - +lint
+    - +test
+    lint:
+    - _echo linting
+    - _echo warning >&2
+    test:
+    - +unit
+    unit:
+    - _echo unit tests
+    - _true
+    """)
+    p = run('--prefix-output', 'all')
+    assert p.returncode == 0, (p.stdout, p.stderr)
+    assert p.stdout == (
+        'all › lint | linting\n'
+        'all › test › unit | unit tests\n'
+    )
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stderr) == (
+        'Running command "all" from donkey-make.yaml...\n'
+        'all › lint ! warning\n'
+        'Command "all" successful in XXms 👍\n'
+    )
+
+
+def test_quiet(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+    - echo foo
+    - +bar
+    bar:
+    - echo bar
+    """)
+    p = run('-q', 'foo', combine=True)
+    assert p.returncode == 0
+    assert p.stdout == 'foo\nbar\n'
+
+
+def test_verbose(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+    - +bar
+    bar:
+    - echo bar
+    """)
+    p = run('-v', 'foo', combine=True)
+    assert p.returncode == 0
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stdout) == (
+        'Running command "foo" from donkey-make.yaml...\n'
+        'Running command "bar" from {}/donkey-make.yaml...\n'
+        '» bar › echo bar\n'
+        'bar\n'
+        'Command "bar" successful in XXms 👍\n'
+        'Command "foo" successful in XXms 👍\n'
+    ).format(test_path.path)
+
+
+def test_very_verbose(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run:
+      - echo $foo
+      env:
+        foo: bar
+    """)
+    p = run('-vv', 'foo')
+    assert p.returncode == 0
+    assert p.stdout == 'bar\n'
+    assert 'Environment:\n  foo=bar\n' in p.stderr
+    assert 'DONKEY_MAKE_VERBOSITY=3\n' in p.stderr
+    assert '\nset -e\n__donk_failed() {\n' in p.stderr
+    assert '\ntrap \'__donk_failed $LINENO\' ERR\n>&2 echo \'» echo $foo\'\necho $foo\n' in p.stderr
+
+
+def test_dry_run(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run:
+      - <bar
+      - echo $API_TOKEN
+      env:
+        API_TOKEN: abc
+    bar:
+    - echo bar
+    """)
+    p = run('--dry-run', 'foo', 'x')
+    assert p.returncode == 0, p.stderr
+    assert p.stderr == ''
+    assert list(test_path.path.iterdir()) == [test_path.path / 'donkey-make.yaml']
+    head, script = p.stdout.split('Script:\n')
+    assert head == (
+        'Executable:  bash\n'
+        'Arguments:   .donk.tmp x\n'
+        'Working dir: {}\n'
+        'Environment:\n'
+        '  API_TOKEN=****\n'
+        '  DONKEY_MAKE_DEPTH=1\n'
+        '  DONKEY_MAKE_CONFIG_FILE={}/donkey-make.yaml\n'
+        '  DONKEY_MAKE_COMMAND=»\n'
+        '  DONKEY_MAKE_KEEP=0\n'
+        '  DONKEY_MAKE_VERBOSITY=1\n'
+    ).format(test_path.path, test_path.path)
+    assert 'abc' not in script
+    assert script.endswith(
+        'set -e\n'
+        '__donk_failed() {\n'
+        '  case $1 in\n'
+        "    17) >&2 echo 'foo › bar run[0] failed (donkey-make.yaml:9): echo bar' ;;\n"
+        "    19) >&2 echo 'foo run[1] failed (donkey-make.yaml:5): echo $API_TOKEN' ;;\n"
+        '  esac\n'
+        '}\n'
+        "trap '__donk_failed $LINENO' ERR\n"
+        ">&2 echo '» bar › echo bar'\n"
+        'echo bar $@\n'
+        ">&2 echo '» echo $API_TOKEN'\n"
+        'echo $API_TOKEN\n'
+    )
+
+
+DOCKER_STUB = """\
+#!/bin/bash
+echo "$(basename "$0") $*" >&2
+# run what would be run in the container directly
+while [ "$1" != "alpine" ]; do shift; done
+shift
+exec "$@"
+"""
+
+
+def test_container(exe, test_path: TPath):
+    test_path.write_file('bin/docker', DOCKER_STUB)
+    os.chmod(str(test_path.path / 'bin/docker'), 0o755)
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run: echo "foo $1 $THING"
+      env:
+        THING: x
+      container:
+        image: alpine
+        volumes:
+        - ./cache:/cache
+    """)
+    env = dict(os.environ, PATH='{}:{}'.format(test_path.path / 'bin', os.environ['PATH']))
+    env = {k: v for k, v in env.items() if not k.startswith('DONKEY_')}
+    p = subprocess.run((str(exe), 'foo', 'a'), stdout=PIPE, stderr=PIPE, universal_newlines=True, env=env)
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'foo a x\n'
+    path = test_path.path.resolve()
+    assert re.sub(r'[\d.]+ms', 'XXms', p.stderr) == (
+        'Running command "foo" from donkey-make.yaml...\n'
+        'docker run --rm --interactive --init --volume {path}:{path} --volume {path}/cache:/cache --workdir {path} '
+        '--env THING --env DONKEY_MAKE_DEPTH --env DONKEY_MAKE_CONFIG_FILE --env DONKEY_MAKE_COMMAND '
+        '--env DONKEY_MAKE_KEEP --env DONKEY_MAKE_VERBOSITY alpine bash .donk.tmp a\n'
+        '» echo "foo $1 $THING"\n'
+        'Command "foo" successful in XXms 👍\n'
+    ).format(path=path)
+
+
+def test_container_workdir(exe, test_path: TPath):
+    test_path.write_file('bin/podman', DOCKER_STUB)
+    os.chmod(str(test_path.path / 'bin/podman'), 0o755)
+    test_path.write_file('sub/.gitkeep', '')
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run: _pwd
+      working_dir: sub
+      container:
+        image: alpine
+        engine: podman
+        workdir: /src
+    """)
+    env = dict(os.environ, PATH='{}:{}'.format(test_path.path / 'bin', os.environ['PATH']))
+    env = {k: v for k, v in env.items() if not k.startswith('DONKEY_')}
+    p = subprocess.run((str(exe), 'foo'), stdout=PIPE, stderr=PIPE, universal_newlines=True, env=env)
+    assert p.returncode == 0, p.stderr
+    path = test_path.path.resolve()
+    assert 'podman run --rm --interactive --init --volume {path}:/src --workdir /src/sub --env '.format(path=path) in (
+        p.stderr
+    )
+    p = subprocess.run((str(exe), '--dry-run', 'foo'), stdout=PIPE, stderr=PIPE, universal_newlines=True, env=env)
+    assert p.returncode == 0, p.stderr
+    assert p.stdout.startswith('Container:   podman alpine\nExecutable:  bash\n')
+
+
+def test_container_donk_call(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run: <bar
+      container:
+        image: alpine
+    bar:
+    - echo bar
+    - +spam x
+    spam: echo spam
+    """)
+    p = run('foo')
+    assert p.returncode == 100
+    assert p.stdout == ''
+    assert p.stderr == (
+        '"+spam x" can\'t be used in a command run in a container, donk isn\'t available in the container\n'
+    )
+
+
+def test_explain(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    all:
+      run:
+      - +lint
+      - +test -x
+    lint:
+      run:
+      - <fmt
+      working_dir: /tmp/
+    fmt: cargo fmt
+    test:
+    - +missing
+    """)
+    p = run('--explain', 'all')
+    assert p.returncode == 1
+    assert p.stderr == ''
+    assert p.stdout == (
+        'all donkey-make.yaml:2 ex: bash-smart\n'
+        '├── +lint donkey-make.yaml:6 ex: bash-smart, working dir: /tmp/\n'
+        '│   └── <fmt donkey-make.yaml:10 ex: bash-smart\n'
+        '└── +test donkey-make.yaml:11 ex: bash-smart\n'
+        '    └── +missing ✗ command not found\n'
+        '1 problem found\n'
+    )
+
+
+def test_explain_inline_not_smart(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+    - <bar
+    bar:
+      run: print(123)
+      ex: python
+    """)
+    p = run('--explain', 'foo')
+    assert p.returncode == 1
+    assert p.stdout == (
+        'foo donkey-make.yaml:2 ex: bash-smart\n'
+        '└── <bar donkey-make.yaml:4 ex: python\n'
+        """     ✗ not a bash-smart script, remove "ex:" or use '+' not '<'\n"""
+        '1 problem found\n'
+    )
+
+
+def test_check_valid(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+    - +bar
+    bar: echo bar
+    """)
+    p = run('--check')
+    assert p.returncode == 0
+    assert p.stderr == ''
+    assert p.stdout == 'donkey-make.yaml is valid, 2 commands found\n'
+
+
+def test_check_problems(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', """
+    foo:
+      run:
+      - <bar
+      - +missing
+      - +foo
+      decription: x
+    bar:
+      run: print(1)
+      ex: python
+      working_dir: nowhere
+    spam:
+      run: echo spam
+      watch_debounce: -1
+      log_keep: abc
+    """)
+    p = run('--check')
+    assert p.returncode == 1
+    assert p.stderr == ''
+    assert p.stdout == (
+        'donkey-make.yaml:4:9: foo: sub-command "bar" not a bash-smart script, it can\'t be used inline with \'<\'\n'
+        'donkey-make.yaml:5:9: foo: sub-command "missing" not found\n'
+        'donkey-make.yaml:6:9: recursion cycle: foo › foo\n'
+        'donkey-make.yaml:7:7: foo: unknown key "decription", perhaps you meant "description"?\n'
+        'donkey-make.yaml:11:7: bar.working_dir: "nowhere" is not a directory\n'
+        'donkey-make.yaml:14:7: spam.watch_debounce: watch_debounce must be greater than or equal to 0\n'
+        'donkey-make.yaml:15:7: spam.log_keep: invalid type: string "abc", expected u32\n'
+        '7 problems found in donkey-make.yaml\n'
+    )
+
+
+def test_check_invalid_yaml(run, test_path: TPath):
+    test_path.write_file('donkey-make.yaml', 'foo:\n  - x\n bar: y\n')
+    p = run('--check')
+    assert p.returncode == 1
+    assert p.stdout == (
+        'donkey-make.yaml:3:5: while parsing a block mapping, did not find expected key\n'
+        '1 problem found in donkey-make.yaml\n'
+    )
+
+
+def test_schema(run):
+    p = run('--schema')
+    assert p.returncode == 0
+    assert p.stderr == ''
+    schema = json.loads(p.stdout)
+    assert schema['$schema'] == 'http://json-schema.org/draft-07/schema#'
+    assert list(schema['properties']) == ['.env', '.settings']
+    cmd_schema = schema['definitions']['command']['oneOf'][1]
+    assert list(cmd_schema['properties']) == [
+        'run', 'args', 'env', 'working_dir', 'watch', 'watch_debounce', 'log', 'log_keep', 'ex', 'description',
+        'group', 'hidden', 'aliases', 'complete', 'container', 'shell_options', 'strict', 'retries',
+        'retry_delay', 'retry_backoff', 'retry_on_exit_codes',
+    ]
+    assert cmd_schema['required'] == ['run']
+
+
+LIST_CONFIG = """
+foo:
+  description: the foo
+  run:
+  - echo foo
+  - |
+    echo bar
+    echo baz
+  env:
+    X: '1'
+  working_dir: sub
+bar: echo bar
+"""
+
+
+def test_list_plain(run, test_path: TPath):
+    test_path.write_file('donk.yml', LIST_CONFIG)
+    p = run('--list')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'foo\tthe foo\tbash\t3\tX=1\tsub\t\nbar\techo bar\tbash\t1\t\t\t\n'
+
+
+def test_list_json(run, test_path: TPath):
+    test_path.write_file('donk.yml', LIST_CONFIG)
+    p = run('--list', '--format', 'json')
+    assert p.returncode == 0, p.stderr
+    assert json.loads(p.stdout) == [
+        {
+            'name': 'foo',
+            'aliases': [],
+            'description': 'the foo',
+            'executable': 'bash',
+            'lines': 3,
+            'env': {'X': '1'},
+            'working_dir': 'sub',
+            'watch': None,
+            'group': None,
+            'hidden': False,
+        },
+        {
+            'name': 'bar',
+            'aliases': [],
+            'description': 'echo bar',
+            'executable': 'bash',
+            'lines': 1,
+            'env': {},
+            'working_dir': None,
+            'watch': None,
+            'group': None,
+            'hidden': False,
+        },
+    ]
+
+
+def test_list_yaml(run, test_path: TPath):
+    test_path.write_file('donk.yml', LIST_CONFIG)
+    p = run('--list', '--format', 'yaml')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout.startswith(
+        '- name: foo\n'
+        '  aliases: []\n'
+        '  description: the foo\n'
+        '  executable: bash\n'
+        '  lines: 3\n'
+        '  env:\n'
+        '    X: "1"\n'
+        '  working_dir: sub\n'
+        '  watch: ~\n'
+        '  group: ~\n'
+        '  hidden: false\n'
+        '- name: bar\n'
+    )
+
+
+def test_list_invalid_format(run, test_path: TPath):
+    test_path.write_file('donk.yml', LIST_CONFIG)
+    p = run('--list', '--format', 'xml')
+    assert p.returncode == 1
+    assert "'xml' isn't a valid value for '--format <format>'" in p.stderr
+
+
+GROUPS_CONFIG = """
+build:
+  run: echo build
+  group: Development
+lint:
+  run: echo lint
+  group: Checks
+test:
+  run: echo test
+  group: Development
+  description: run the tests
+deploy: echo deploy
+_internal: echo internal
+secret:
+  run: echo secret
+  hidden: true
+"""
+
+
+def test_groups(run, test_path: TPath):
+    test_path.write_file('donk.yml', GROUPS_CONFIG)
+    p = run()
+    assert p.returncode == 0, p.stderr
+    assert re.sub(r'v[\d.]+', 'v0.0.0', p.stdout) == (
+        'donkey-make v0.0.0, commands available from donk.yml:\n'
+        '  deploy         (1 line) echo deploy\n'
+        '\n'
+        'Development:\n'
+        '  build          (1 line) echo build\n'
+        '  test           (1 line) run the tests\n'
+        '\n'
+        'Checks:\n'
+        '  lint           (1 line) echo lint\n'
+        '\n'
+        '2 hidden commands, use "--all" to show them\n'
+    )
+    p = run('secret')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'secret\n'
+
+
+def test_groups_all(run, test_path: TPath):
+    test_path.write_file('donk.yml', GROUPS_CONFIG)
+    p = run('--all')
+    assert p.returncode == 0, p.stderr
+    assert re.sub(r'v[\d.]+', 'v0.0.0', p.stdout).startswith(
+        'donkey-make v0.0.0, commands available from donk.yml:\n'
+        '  deploy         (1 line) echo deploy\n'
+        '  _internal      (1 line) echo internal\n'
+        '  secret         (1 line) echo secret\n'
+        '\n'
+        'Development:\n'
+    )
+    assert 'hidden' not in p.stdout
+    p = run('--list', '--all')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout.count('\n') == 6
+
+
+ALIASES_CONFIG = """
+test:
+  run: echo testing
+  aliases: [t]
+format:
+  aliases: [fmt, f]
+  run:
+  - <t
+  - echo formatting
+"""
+
+
+def test_aliases(run, test_path: TPath):
+    test_path.write_file('donk.yml', ALIASES_CONFIG)
+    p = run()
+    assert p.returncode == 0, p.stderr
+    assert re.sub(r'v[\d.]+', 'v0.0.0', p.stdout) == (
+        'donkey-make v0.0.0, commands available from donk.yml:\n'
+        '  test, t        (1 line) echo testing\n'
+        '  format, fmt, f (2 lines) <t…\n'
+    )
+    p = run('fmt')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'testing\nformatting\n'
+    assert p.stderr.startswith('Running command "format" from donk.yml...\n')
+    p = run('--complete-command')
+    assert p.stdout == 'test format t fmt f\n'
+
+
+def test_alias_suggestion(run, test_path: TPath):
+    test_path.write_file('donk.yml', ALIASES_CONFIG)
+    p = run('fmtt')
+    assert p.returncode == 100
+    assert p.stderr == (
+        'Command "fmtt" not found, commands available are:\n'
+        '  test, format\n'
+        '\n'
+        '    perhaps you meant "fmt"?\n'
+    )
+
+
+def test_alias_clash(run, test_path: TPath):
+    test_path.write_file('donk.yml', """
+a:
+  run: echo a
+  aliases: [b]
+b:
+  run: echo b
+  aliases: [c]
+c:
+  run: echo c
+  aliases: [x]
+d:
+  run: echo d
+  aliases: [x]
+""")
+    p = run('a')
+    assert p.returncode == 100
+    assert p.stderr == 'Error parsing donk.yml:\n  alias "b" of command "a" clashes with command "b" on line 2\n'
+    p = run('--check')
+    assert p.returncode == 1
+    assert p.stdout == (
+        'donk.yml:4:3: a.aliases: alias "b" of command "a" clashes with command "b"\n'
+        'donk.yml:7:3: b.aliases: alias "c" of command "b" clashes with command "c"\n'
+        'donk.yml:13:3: d.aliases: alias "x" of command "d" is also an alias of command "c"\n'
+        '3 problems found in donk.yml\n'
+    )
+
+
+PREFIX_CONFIG = """
+.settings:
+  prefix_matching: true
+build: echo building
+bundle: echo bundling
+test:
+  run: echo testing
+  aliases: [check]
+"""
+
+
+def test_prefix_matching(run, test_path: TPath):
+    test_path.write_file('donk.yml', PREFIX_CONFIG)
+    p = run('bui')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'building\n'
+    p = run('che')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'testing\n'
+
+
+def test_prefix_matching_ambiguous(run, test_path: TPath):
+    test_path.write_file('donk.yml', PREFIX_CONFIG)
+    p = run('bu')
+    assert p.returncode == 100
+    assert p.stderr == 'Command "bu" is ambiguous, it could be any of:\n  build, bundle\n'
+
+
+def test_prefix_matching_off(run, test_path: TPath):
+    test_path.write_file('donk.yml', PREFIX_CONFIG.replace('true', 'false'))
+    p = run('bui')
+    assert p.returncode == 100
+    assert p.stderr.startswith('Command "bui" not found, commands available are:\n')
+
+
+SEQUENCE_CONFIG = """
+lint:
+- _echo linting $@
+- _echo done
+test:
+- _echo testing
+- _exit 3
+build:
+- _echo building $@
+- _echo done
+"""
+
+
+def test_multiple_commands(run, test_path: TPath):
+    test_path.write_file('donk.yml', SEQUENCE_CONFIG)
+    p = run('lint', 'build')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'linting\ndone\nbuilding\ndone\n'
+    assert p.stderr.startswith('Running commands "lint", "build" from donk.yml...\n')
+    assert re.sub(r'in [\d.]+ms', 'in XXms', p.stderr).endswith('Commands "lint", "build" successful in XXms 👍\n')
+
+
+def test_multiple_commands_separator(run, test_path: TPath):
+    test_path.write_file('donk.yml', SEQUENCE_CONFIG)
+    p = run('lint', 'a', '+', 'build', 'b', '--', '-x')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'linting a\ndone\nbuilding b -x\ndone\n'
+
+
+def test_multiple_commands_fail_fast(run, test_path: TPath):
+    test_path.write_file('donk.yml', SEQUENCE_CONFIG)
+    p = run('lint', 'test', 'build')
+    assert p.returncode == 3, p.stderr
+    assert p.stdout == 'linting\ndone\ntesting\n'
+    assert re.sub(r'in [\d.]+ms', 'in XXms', p.stderr).endswith(
+        'Commands "lint", "test", "build" failed in XXms, "test" exit code 3, skipped "build" 👎\n'
+    )
+
+
+def test_multiple_commands_keep_going(run, test_path: TPath):
+    test_path.write_file('donk.yml', SEQUENCE_CONFIG)
+    p = run('--keep-going', 'lint', 'test', 'build')
+    assert p.returncode == 3, p.stderr
+    assert p.stdout == 'linting\ndone\ntesting\nbuilding\ndone\n'
+
+
+def test_multiple_commands_not_all_commands(run, test_path: TPath):
+    test_path.write_file('donk.yml', SEQUENCE_CONFIG)
+    p = run('lint', 'x', 'build')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'linting x build\ndone\n'
+    p = run('lint', '--', 'build')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'linting build\ndone\n'
+
+
+def test_import_makefile(run, test_path: TPath):
+    test_path.write_file('Makefile', """\
+SRC = src
+.PHONY: install lint
+
+install: ## install dependencies
+\techo installing
+
+# run linting
+lint: install
+\t@echo flake8 $(SRC)
+\t-false
+
+%.o: %.c
+\tgcc -c $<
+
+all: lint test
+""")
+    p = run('import')
+    assert p.returncode == 0, p.stderr
+    assert p.stderr == (
+        'warning: Makefile:12: pattern or variable target "%.o" not supported, ignored\n'
+        'warning: Makefile:15: prerequisite "test" of "all" is not a target, ignored\n'
+    )
+    assert p.stdout == (
+        '# imported from Makefile by donk import\n'
+        '".env":\n'
+        '  SRC: src\n'
+        'install:\n'
+        '  description: install dependencies\n'
+        '  run:\n'
+        '    - echo installing\n'
+        'lint:\n'
+        '  description: run linting\n'
+        '  run:\n'
+        '    - +install\n'
+        '    - "_echo flake8 ${SRC}"\n'
+        '    - false || true\n'
+        'all:\n'
+        '  run:\n'
+        '    - +lint\n'
+    )
+    test_path.write_file('donk.yml', p.stdout)
+    p = run('all')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'installing\nflake8 src\n'
+
+
+def test_import_package_json(run, test_path: TPath):
+    test_path.write_file('package.json', json.dumps({
+        'scripts': {'pretest': 'npm run lint', 'test': 'echo jest', 'lint': 'echo eslint'}
+    }))
+    p = run('import', 'package.json')
+    assert p.returncode == 0, p.stderr
+    assert 'node_modules/.bin' in p.stderr
+    test_path.write_file('donk.yml', p.stdout)
+    p = run('test')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'eslint\njest\n'
+
+
+def test_import_justfile(run, test_path: TPath):
+    test_path.write_file('justfile', """\
+set shell := ["bash", "-c"]
+version := "1.2"
+
+# build the project
+build target='debug': clean
+    echo building {{target}} {{version}}
+
+clean:
+    @echo cleaning
+""")
+    p = run('import')
+    assert p.returncode == 0, p.stderr
+    assert p.stderr == 'warning: justfile:1: "set" not supported, ignored\n'
+    test_path.write_file('donk.yml', p.stdout)
+    p = run('build', 'release')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'cleaning\nbuilding release 1.2\n'
+
+
+def test_import_shadowed(run, test_path: TPath):
+    test_path.write_file('donk.yml', 'import:\n- echo "not importing"\n- echo "..."')
+    p = run('import')
+    assert p.returncode == 0, p.stderr
+    assert p.stdout == 'not importing\n...\n'
+
+
+def test_import_nothing_found(run, test_path: TPath):
+    p = run('import')
+    assert p.returncode == 100
+    assert p.stderr.startswith('No file to import found, tried:\n')
+
+
 def test_bash_completion_script(run):
     p = run('--completion-script')
     assert p.returncode == 0
@@ -332,6 +1628,113 @@ def test_bash_command_completion_custom_file_missing(run, test_path: TPath):
     assert p.stderr == ''
 
 
+def test_zsh_completion_script(run):
+    p = run('--completion-script', 'zsh')
+    assert p.returncode == 0
+    assert p.stderr == ''
+    assert p.stdout.startswith('#compdef donk\n# donk zsh-completion script\n')
+    assert 'donk --complete --described' in p.stdout
+
+
+def test_fish_completion_script(run):
+    p = run('--completion-script', 'fish')
+    assert p.returncode == 0
+    assert p.stderr == ''
+    assert p.stdout.startswith('# donk fish-completion script\n')
+    assert 'donk --complete --described' in p.stdout
+
+
+def test_command_completion_described(run, test_path: TPath):
+    test_path.write_file('donk.yml', """
+    foo:
+      run: xxx
+      description: the foo
+      aliases: [f]
+    bar: yyy
+    _hidden: zzz
+    """)
+    p = run('--complete', '--described', '1', '--', 'donk', '')
+    assert p.returncode == 0
+    assert p.stdout == 'foo\tthe foo\nbar\tyyy\nf\tthe foo\n'
+    assert p.stderr == ''
+
+
+COMPLETE_ARGS_CONFIG = """
+test:
+  run: echo $@
+  complete: [--verbose, --lf]
+  aliases: [t]
+lint:
+  run: echo lint
+  complete: ls *.txt
+  working_dir: sub
+broken:
+  run: echo
+  complete: exit 1
+other: echo
+"""
+
+
+def test_complete_args_values(run, test_path: TPath):
+    test_path.write_file('donk.yml', COMPLETE_ARGS_CONFIG)
+    p = run('--complete', '2', '--', 'donk', 't', '')
+    assert p.returncode == 0
+    assert p.stdout == '--verbose\n--lf\n'
+    assert p.stderr == ''
+
+
+def test_complete_args_script(run, test_path: TPath):
+    test_path.write_file('other/donk.yml', COMPLETE_ARGS_CONFIG)
+    test_path.write_file('other/sub/a.txt', '')
+    test_path.write_file('other/sub/b.txt', '')
+    p = run('--complete', '4', '--', 'donk', '-f', 'other/donk.yml', 'lint', '')
+    assert p.returncode == 0
+    assert p.stdout == 'a.txt\nb.txt\n'
+    assert p.stderr == ''
+
+
+def test_complete_args_none(run, test_path: TPath):
+    test_path.write_file('donk.yml', COMPLETE_ARGS_CONFIG)
+    for cmd in ('broken', 'other', 'missing'):
+        p = run('--complete', '2', '--', 'donk', cmd, '')
+        assert p.returncode == 0
+        assert p.stdout == ''
+        assert p.stderr == ''
+
+
+def test_complete_file_option(run, test_path: TPath):
+    test_path.write_file('donk.yml', 'foo: xxx')
+    test_path.write_file('other.yml', 'bar: yyy')
+    p = run('--complete', '3', '--', 'donk', '-f', 'other.yml', '')
+    assert p.returncode == 0
+    assert p.stdout == 'bar\n'
+    p = run('--complete', '2', '--', 'donk', '--file=other.yml', 'b')
+    assert p.stdout == 'bar\n'
+    p = run('--complete', '2', '--', 'donk', './other.yml', '')
+    assert p.stdout == 'bar\n'
+    p = run('--complete', '1', '--', 'donk', '')
+    assert p.stdout == 'foo\n'
+
+
+def test_complete_config_file_env(exe, test_path: TPath):
+    test_path.write_file('donk.yml', 'foo: xxx')
+    test_path.write_file('other.yml', 'bar: yyy')
+    env = dict(os.environ, DONKEY_MAKE_CONFIG_FILE=str(test_path.path / 'other.yml'))
+    p = subprocess.run(
+        (str(exe), '--complete', '1', '--', 'donk', ''), stdout=PIPE, stderr=PIPE, universal_newlines=True, env=env
+    )
+    assert p.returncode == 0
+    assert p.stdout == 'bar\n'
+
+
+def test_complete_after_separator(run, test_path: TPath):
+    test_path.write_file('donk.yml', COMPLETE_ARGS_CONFIG)
+    p = run('--complete', '3', '--', 'donk', 'test', '+', '')
+    assert p.stdout == 'test\nlint\nbroken\nother\nt\n'
+    p = run('--complete', '2', '--', 'donk', '--format', '')
+    assert p.stdout == ''
+
+
 # def kill_donk():
 #     sleep(1)
 #